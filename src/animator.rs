@@ -1,50 +1,437 @@
-use bevy::{core::FixedTimestep, prelude::*};
+mod atlas;
+mod definition;
+mod graph;
+
+pub use atlas::*;
+use bevy::{prelude::*, utils::HashMap};
+#[cfg(feature = "dev")]
 use bevy_inspector_egui::Inspectable;
+pub use definition::*;
+pub use graph::*;
+use serde::Deserialize;
+
+use crate::{AppState, GameTime, SpriteEffects};
+
+/// Seconds each frame is shown, unless the animation says otherwise.
+pub const FRAME_DURATION: f32 = 1. / 8.;
 
-use crate::AppState;
+/// What happens once the last frame of an animation was shown.
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Repeat {
+    /// Starts again from the first frame.
+    #[default]
+    Loop,
+    /// Plays once, then goes back to the animation it interrupted.
+    Once,
+    /// Plays forward then backward, and again.
+    PingPong,
+    /// Plays once, then stays on the last frame.
+    HoldLast,
+}
+
+/// Sent when a `Once` or `HoldLast` animation played its last frame.
+pub struct AnimationFinished {
+    pub entity: Entity,
+    pub animation: String,
+}
 
-#[derive(Inspectable, Component, Default)]
+/// Sent when an animation reaches one of its tagged frames.
+pub struct AnimationEvent {
+    pub entity: Entity,
+    pub animation: String,
+    pub tag: String,
+}
+
+/// Frames of a sprite sheet, shown `frame_duration` seconds each.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    frames: Vec<usize>,
+    frame_duration: f32,
+    repeat: Repeat,
+    /// Tags sent as `AnimationEvent`s when reaching the frame at their position in `frames`.
+    events: Vec<(usize, String)>,
+}
+impl Animation {
+    /// Looping animation.
+    pub fn new(frames: Vec<usize>, frame_duration: f32) -> Self {
+        Animation {
+            frames,
+            frame_duration,
+            repeat: Repeat::Loop,
+            events: vec![],
+        }
+    }
+
+    pub fn ping_pong(self) -> Self {
+        self.with_repeat(Repeat::PingPong)
+    }
+
+    pub fn hold_last(self) -> Self {
+        self.with_repeat(Repeat::HoldLast)
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Tags the `frame`-th frame of the animation with `tag`.
+    pub fn with_event(mut self, frame: usize, tag: &str) -> Self {
+        self.events.push((frame, tag.to_string()));
+        self
+    }
+
+    fn tags(&self, frame: usize) -> impl Iterator<Item = &str> {
+        self.events
+            .iter()
+            .filter(move |(tagged, _)| *tagged == frame)
+            .map(|(_, tag)| tag.as_str())
+    }
+}
+
+/// Plays named animations on a `TextureAtlasSprite`, each at its own pace.
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component, Default)]
 pub struct Animator {
-    animations: Vec<Vec<usize>>,
-    curr_animation: usize,
-    curr_sprite: usize,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    animations: HashMap<String, Animation>,
+    current: String,
+    /// Animation resumed once the current `Once` animation is over.
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    interrupted: Option<String>,
+    frame: usize,
     flip_x: bool,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    backward: bool,
+    /// Whether the tags of the first frame are still to be sent.
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    entered: bool,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    finished: bool,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    timer: Timer,
 }
 impl Animator {
-    pub fn new(animations: Vec<Vec<usize>>) -> Self {
-        Animator {
-            animations,
-            curr_animation: 0,
-            curr_sprite: 0,
-            flip_x: false,
+    /// Animator playing the first of `animations`.
+    pub fn new(animations: Vec<(&str, Animation)>) -> Self {
+        let mut animator = Animator::default();
+        if let Some((name, _)) = animations.first() {
+            animator.current = name.to_string();
+        }
+        animator.animations = animations
+            .into_iter()
+            .map(|(name, animation)| (name.to_string(), animation))
+            .collect();
+        animator.restart();
+        animator
+    }
+
+    pub fn animation_is(&self, name: &str, flip_x: bool) -> bool {
+        self.current == name && self.flip_x == flip_x
+    }
+
+    pub fn is_playing(&self, name: &str) -> bool {
+        self.current == name
+    }
+
+    pub fn has_animation(&self, name: &str) -> bool {
+        self.animations.contains_key(name)
+    }
+
+    /// Whether a `Once` or `HoldLast` animation played its last frame.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn flip_x(&self) -> bool {
+        self.flip_x
+    }
+
+    /// Replaces the animations, keeping on with the current one if still there, `initial` otherwise.
+    pub fn set_animations(&mut self, animations: Vec<(&str, Animation)>, initial: &str) {
+        self.animations = animations
+            .into_iter()
+            .map(|(name, animation)| (name.to_string(), animation))
+            .collect();
+        if !self.animations.contains_key(&self.current) {
+            self.current = initial.to_string();
+            self.interrupted = None;
+            self.restart();
+            return;
+        }
+        if let Some(animation) = self.animations.get(&self.current) {
+            self.frame = self.frame.min(animation.frames.len().saturating_sub(1));
+            self.timer = Timer::from_seconds(animation.frame_duration, true);
+        }
+    }
+
+    pub fn change_animation(&mut self, name: &str, flip_x: bool) {
+        if self.animation_is(name, flip_x) {
+            return;
+        }
+        let once = self
+            .animations
+            .get(name)
+            .is_some_and(|animation| animation.repeat == Repeat::Once);
+        if !once {
+            self.interrupted = None;
+        } else if self.interrupted.is_none() && self.current != name {
+            self.interrupted = Some(self.current.clone());
         }
+        self.current = name.to_string();
+        self.flip_x = flip_x;
+        self.restart();
     }
 
-    pub fn animation_is(&self, anim: usize, flip_x: bool) -> bool {
-        self.curr_animation == anim && self.flip_x == flip_x
+    fn restart(&mut self) {
+        let frame_duration = self
+            .animations
+            .get(&self.current)
+            .map_or(FRAME_DURATION, |animation| animation.frame_duration);
+        self.frame = 0;
+        self.backward = false;
+        self.entered = true;
+        self.finished = false;
+        self.timer = Timer::from_seconds(frame_duration, true);
     }
 
-    pub fn change_animation(&mut self, anim: usize, flip_x: bool) {
-        if !self.animation_is(anim, flip_x) {
-            self.curr_animation = anim;
-            self.curr_sprite = 0;
-            self.flip_x = flip_x;
+    /// Moves to the next frame, returning whether the animation is over.
+    fn advance(&mut self, repeat: Repeat, last: usize) -> bool {
+        match repeat {
+            Repeat::Loop => self.frame = if self.frame < last { self.frame + 1 } else { 0 },
+            Repeat::Once | Repeat::HoldLast if self.frame < last => self.frame += 1,
+            Repeat::Once | Repeat::HoldLast => return true,
+            Repeat::PingPong if last == 0 => {}
+            Repeat::PingPong => {
+                if self.frame == 0 {
+                    self.backward = false;
+                } else if self.frame == last {
+                    self.backward = true;
+                }
+                self.frame = if self.backward {
+                    self.frame - 1
+                } else {
+                    self.frame + 1
+                };
+            }
         }
+        false
     }
 
-    pub fn animate(mut query: Query<(&mut Animator, &mut TextureAtlasSprite)>) {
-        for (mut animator, mut sprite) in query.iter_mut() {
-            let anim = &animator.animations[animator.curr_animation];
-            let next_sprite = (animator.curr_sprite + 1) % anim.len();
-            sprite.index = anim[next_sprite];
-            animator.curr_sprite = next_sprite;
+    pub fn animate(
+        time: Res<GameTime>,
+        mut finished_events: EventWriter<AnimationFinished>,
+        mut frame_events: EventWriter<AnimationEvent>,
+        mut query: Query<(Entity, &mut Animator, &mut TextureAtlasSprite)>,
+    ) {
+        for (entity, mut animator, mut sprite) in query.iter_mut() {
+            // Taken out while playing, so the animation can be read as the animator changes.
+            let animations = std::mem::take(&mut animator.animations);
+            let animation = match animations.get(&animator.current) {
+                Some(animation) if !animation.frames.is_empty() => animation,
+                _ => {
+                    animator.animations = animations;
+                    continue;
+                }
+            };
+            let last = animation.frames.len() - 1;
+
+            if animator.entered {
+                animator.entered = false;
+                send_tags(&mut frame_events, entity, &animator, animation);
+            }
+
+            animator.timer.tick(time.delta());
+            for _ in 0..animator.timer.times_finished() {
+                if animator.finished {
+                    break;
+                }
+                if animator.advance(animation.repeat, last) {
+                    animator.finished = true;
+                    finished_events.send(AnimationFinished {
+                        entity,
+                        animation: animator.current.clone(),
+                    });
+                } else {
+                    send_tags(&mut frame_events, entity, &animator, animation);
+                }
+            }
+            let resume = animator.finished && animation.repeat == Repeat::Once;
+            animator.animations = animations;
+
+            if resume {
+                if let Some(interrupted) = animator.interrupted.take() {
+                    let flip_x = animator.flip_x;
+                    animator.change_animation(&interrupted, flip_x);
+                }
+            }
+
+            if let Some(animation) = animator.animations.get(&animator.current) {
+                if let Some(&index) = animation.frames.get(animator.frame) {
+                    sprite.index = index;
+                }
+            }
             sprite.flip_x = animator.flip_x;
         }
     }
 
     pub fn system_set() -> SystemSet {
         SystemSet::on_update(AppState::RunningGame)
-            .with_run_criteria(FixedTimestep::step(1. / 8.))
+            .with_system(AnimationSource::apply.before(Animator::animate))
             .with_system(Animator::animate)
     }
 }
+
+/// Animations, their RON definitions, state graphs and sprite effects.
+pub struct AnimationPlugin;
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<CharacterAnimations>()
+            .init_asset_loader::<CharacterAnimationsLoader>()
+            .add_event::<AnimationFinished>()
+            .add_event::<AnimationEvent>()
+            .add_system_set(Animator::system_set())
+            .add_system_set(AnimationGraph::system_set())
+            .add_system_set(SpriteEffects::system_set());
+    }
+}
+
+fn send_tags(
+    frame_events: &mut EventWriter<AnimationEvent>,
+    entity: Entity,
+    animator: &Animator,
+    animation: &Animation,
+) {
+    for tag in animation.tags(animator.frame) {
+        frame_events.send(AnimationEvent {
+            entity,
+            animation: animator.current.clone(),
+            tag: tag.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::event::Events;
+    use std::time::Duration;
+
+    const STEP: f32 = 0.1;
+
+    /// World holding a single animator, stepped one frame at a time.
+    struct Playback {
+        world: World,
+        stage: SystemStage,
+        entity: Entity,
+    }
+    impl Playback {
+        fn new(animator: Animator) -> Self {
+            let mut world = World::new();
+            world.insert_resource(GameTime::manual());
+            world.init_resource::<Events<AnimationFinished>>();
+            world.init_resource::<Events<AnimationEvent>>();
+            let entity = world
+                .spawn()
+                .insert(animator)
+                .insert(TextureAtlasSprite::default())
+                .id();
+            let stage = SystemStage::single_threaded().with_system(Animator::animate);
+            Playback {
+                world,
+                stage,
+                entity,
+            }
+        }
+
+        /// Shows the next frame, returning the index shown.
+        fn step(&mut self) -> usize {
+            self.world
+                .resource_mut::<GameTime>()
+                .advance(Duration::from_secs_f32(STEP));
+            self.stage.run(&mut self.world);
+            self.world
+                .get::<TextureAtlasSprite>(self.entity)
+                .unwrap()
+                .index
+        }
+
+        fn steps(&mut self, count: usize) -> Vec<usize> {
+            (0..count).map(|_| self.step()).collect()
+        }
+
+        fn animator(&mut self) -> Mut<'_, Animator> {
+            self.world.get_mut::<Animator>(self.entity).unwrap()
+        }
+
+        fn finished(&mut self) -> Vec<String> {
+            let mut events = self.world.resource_mut::<Events<AnimationFinished>>();
+            events.drain().map(|event| event.animation).collect()
+        }
+
+        fn tags(&mut self) -> Vec<String> {
+            let mut events = self.world.resource_mut::<Events<AnimationEvent>>();
+            events.drain().map(|event| event.tag).collect()
+        }
+    }
+
+    fn animation(frames: &[usize]) -> Animation {
+        // Slightly shorter than a step, so that each step shows exactly one more frame.
+        Animation::new(frames.to_vec(), STEP * 0.99)
+    }
+
+    #[test]
+    fn loops_back_to_the_first_frame() {
+        let mut playback = Playback::new(Animator::new(vec![("walk", animation(&[10, 11, 12]))]));
+        assert_eq!(playback.steps(4), [11, 12, 10, 11]);
+        assert!(playback.finished().is_empty());
+    }
+
+    #[test]
+    fn ping_pongs_between_the_ends() {
+        let walk = animation(&[10, 11, 12]).ping_pong();
+        let mut playback = Playback::new(Animator::new(vec![("walk", walk)]));
+        assert_eq!(playback.steps(6), [11, 12, 11, 10, 11, 12]);
+    }
+
+    #[test]
+    fn holds_the_last_frame() {
+        let death = animation(&[10, 11]).hold_last();
+        let mut playback = Playback::new(Animator::new(vec![("death", death)]));
+        assert_eq!(playback.steps(4), [11, 11, 11, 11]);
+        assert_eq!(playback.finished(), ["death"]);
+        assert!(playback.animator().is_finished());
+    }
+
+    #[test]
+    fn resumes_the_interrupted_animation_after_once() {
+        let mut playback = Playback::new(Animator::new(vec![
+            ("idle", animation(&[1, 2])),
+            ("attack", animation(&[10, 11]).with_repeat(Repeat::Once)),
+        ]));
+        playback.step();
+        playback.animator().change_animation("attack", false);
+
+        // The first frame is shown as soon as the animation changes.
+        assert_eq!(playback.steps(2), [11, 1]);
+        assert_eq!(playback.finished(), ["attack"]);
+        assert!(playback.animator().is_playing("idle"));
+    }
+
+    #[test]
+    fn sends_the_tags_of_the_frames_reached() {
+        let attack = animation(&[10, 11, 12])
+            .hold_last()
+            .with_event(0, "windup")
+            .with_event(2, "hit");
+        let mut playback = Playback::new(Animator::new(vec![("attack", attack)]));
+
+        playback.step();
+        assert_eq!(playback.tags(), ["windup"]);
+        playback.step();
+        assert_eq!(playback.tags(), ["hit"]);
+        playback.steps(2);
+        assert!(playback.tags().is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+use super::{Animation, CharacterAnimations, FRAME_DURATION};
+use crate::SpriteGrid;
+use bevy::{prelude::*, utils::HashMap};
+use std::path::Path;
+
+/// Parts of a sprite file named `Character_Action_Variant_Frame.png`, such as
+/// `Player_Walk_Sword_Defence0_2.png` or `Goblin_Death_3.png`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SpriteName {
+    pub character: String,
+    /// `Idle` for sprites named after their character only, like `HP_0.png`.
+    pub action: String,
+    /// Every part between the action and the frame, joined by `_`.
+    pub variant: String,
+    /// `0` for sprites without a frame number, like `GoblinDoor_Open.png`.
+    pub frame: usize,
+}
+impl SpriteName {
+    pub fn parse(path: &Path) -> Option<Self> {
+        let stem = path.file_stem()?.to_str()?;
+        let mut parts: Vec<&str> = stem.split('_').collect();
+
+        let frame = match parts.last()?.parse() {
+            Ok(frame) if parts.len() > 1 => {
+                parts.pop();
+                frame
+            }
+            _ => 0,
+        };
+        let character = parts.first()?.to_string();
+        let action = parts.get(1).copied().unwrap_or("Idle").to_string();
+        let variant = parts
+            .get(2..)
+            .map(|parts| parts.join("_"))
+            .unwrap_or_default();
+
+        Some(SpriteName {
+            character,
+            action,
+            variant,
+            frame,
+        })
+    }
+}
+
+/// Texture atlas along with the frames of each of its animations, by name.
+#[derive(Clone, Default)]
+pub struct SpriteSheet {
+    pub atlas: Handle<TextureAtlas>,
+    animations: HashMap<String, Vec<usize>>,
+    /// Animations played with the sheet by an `AnimationSource`.
+    pub(super) definition: Handle<CharacterAnimations>,
+}
+impl SpriteSheet {
+    /// Packs the sprites into an atlas, naming their animations after their lowercase action.
+    ///
+    /// The variant is appended to the name when the sprites don't all share it, so that
+    /// `SlimeKing_Obstacle_Death_0.png` gives `obstacle_death` next to `obstacle`.
+    pub fn build(
+        handles: &[HandleUntyped],
+        asset_server: &AssetServer,
+        textures: &mut Assets<Image>,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let mut atlas_builder = TextureAtlasBuilder::default();
+        let mut sprites = vec![];
+
+        for handle in handles {
+            let texture = textures.get(handle).unwrap();
+            let handle = handle.clone_weak().typed::<Image>();
+            atlas_builder.add_texture(handle.clone(), texture);
+
+            let name = asset_server
+                .get_handle_path(&handle)
+                .and_then(|path| SpriteName::parse(path.path()));
+            match name {
+                Some(name) => sprites.push((name, handle)),
+                None => warn!("Unnamed sprite in atlas: {:?}", handle),
+            }
+        }
+        let atlas = atlas_builder.finish(textures).unwrap();
+
+        let shared_variant = sprites
+            .first()
+            .map(|(name, _)| name.variant.clone())
+            .filter(|variant| sprites.iter().all(|(name, _)| &name.variant == variant));
+        sprites.sort_by_key(|(name, _)| name.frame);
+
+        let mut animations: HashMap<String, Vec<usize>> = HashMap::default();
+        for (name, handle) in &sprites {
+            let key = if shared_variant.is_some() || name.variant.is_empty() {
+                name.action.to_lowercase()
+            } else {
+                format!("{}_{}", name.action, name.variant).to_lowercase()
+            };
+            if let Some(index) = atlas.get_texture_index(handle) {
+                animations.entry(key).or_default().push(index);
+            }
+        }
+
+        SpriteSheet {
+            atlas: texture_atlases.add(atlas),
+            animations,
+            definition: Handle::default(),
+        }
+    }
+
+    /// Cuts `texture` along `grid`, keeping the animations it names.
+    pub fn from_grid(
+        texture: Handle<Image>,
+        grid: &SpriteGrid,
+        texture_atlases: &mut Assets<TextureAtlas>,
+    ) -> Self {
+        let atlas =
+            TextureAtlas::from_grid(texture, Vec2::splat(grid.tile), grid.columns, grid.rows);
+
+        SpriteSheet {
+            atlas: texture_atlases.add(atlas),
+            animations: grid.animations.clone(),
+            definition: Handle::default(),
+        }
+    }
+
+    pub fn with_definition(mut self, definition: Handle<CharacterAnimations>) -> Self {
+        self.definition = definition;
+        self
+    }
+
+    /// Frames of the animation `name`, empty if the sheet has none.
+    pub fn frames(&self, name: &str) -> Vec<usize> {
+        self.animations.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Looping animation of the frames of `name`.
+    pub fn animation(&self, name: &str) -> Animation {
+        Animation::new(self.frames(name), FRAME_DURATION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(path: &str) -> SpriteName {
+        SpriteName::parse(Path::new(path)).unwrap()
+    }
+
+    fn name(character: &str, action: &str, variant: &str, frame: usize) -> SpriteName {
+        SpriteName {
+            character: character.to_string(),
+            action: action.to_string(),
+            variant: variant.to_string(),
+            frame,
+        }
+    }
+
+    #[test]
+    fn parses_character_only() {
+        assert_eq!(parse("UI/HP_0.png"), name("HP", "Idle", "", 0));
+    }
+
+    #[test]
+    fn parses_action_without_frame() {
+        assert_eq!(
+            parse("Doors/GoblinDoor_Open.png"),
+            name("GoblinDoor", "Open", "", 0)
+        );
+    }
+
+    #[test]
+    fn parses_variant_and_frame() {
+        assert_eq!(
+            parse("SlimeKing_Obstacle_Death_0.png"),
+            name("SlimeKing", "Obstacle", "Death", 0)
+        );
+        assert_eq!(
+            parse("Player_Walk_Sword_Defence0_2.png"),
+            name("Player", "Walk", "Sword_Defence0", 2)
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use super::{Animation, Animator, Repeat, SpriteSheet, FRAME_DURATION};
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    sprite::Anchor,
+    utils::{BoxedFuture, HashSet},
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+fn default_frame_duration() -> f32 {
+    FRAME_DURATION
+}
+
+#[derive(Deserialize)]
+struct AnimationDefinition {
+    /// Action of the sprite sheet the frames come from, the name of the animation if missing.
+    #[serde(default)]
+    sprites: Option<String>,
+    /// Positions of the frames among the sprites of the action, all of them in order if missing.
+    #[serde(default)]
+    frames: Option<Vec<usize>>,
+    #[serde(default = "default_frame_duration")]
+    frame_duration: f32,
+    #[serde(default)]
+    repeat: Repeat,
+    /// Tags sent as `AnimationEvent`s, by position in the frames.
+    #[serde(default)]
+    events: Vec<(usize, String)>,
+}
+
+/// Animations of a character, read from an `.anim.ron` file under `assets/animations`.
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "5d3c8e0a-7b1f-4c52-9a66-2f0e4b8d91c7"]
+pub struct CharacterAnimations {
+    /// Animation played until another one is asked for.
+    initial: String,
+    /// Anchor of the sprite, relative to its center.
+    #[serde(default)]
+    anchor: Option<(f32, f32)>,
+    animations: HashMap<String, AnimationDefinition>,
+}
+impl CharacterAnimations {
+    fn build<'a>(&'a self, sheet: &SpriteSheet) -> Vec<(&'a str, Animation)> {
+        self.animations
+            .iter()
+            .map(|(name, definition)| {
+                let sprites = sheet.frames(definition.sprites.as_deref().unwrap_or(name));
+                let frames = match &definition.frames {
+                    Some(frames) => frames
+                        .iter()
+                        .filter_map(|&frame| sprites.get(frame).copied())
+                        .collect(),
+                    None => sprites,
+                };
+                let animation = definition.events.iter().fold(
+                    Animation::new(frames, definition.frame_duration)
+                        .with_repeat(definition.repeat),
+                    |animation, (frame, tag)| animation.with_event(*frame, tag),
+                );
+                (name.as_str(), animation)
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct CharacterAnimationsLoader;
+impl AssetLoader for CharacterAnimationsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let animations: CharacterAnimations = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(animations));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["anim.ron"]
+    }
+}
+
+/// Sprite sheet whose definition fills the holder's `Animator`, again whenever the file changes.
+#[derive(Component, Default)]
+pub struct AnimationSource {
+    sheet: SpriteSheet,
+}
+impl AnimationSource {
+    pub fn new(sheet: &SpriteSheet) -> Self {
+        AnimationSource {
+            sheet: sheet.clone(),
+        }
+    }
+
+    /// Swaps the sprite sheet, such as when the player puts on armor.
+    pub fn set_sheet(&mut self, sheet: &SpriteSheet) {
+        self.sheet = sheet.clone();
+    }
+
+    pub fn apply(
+        mut asset_events: EventReader<AssetEvent<CharacterAnimations>>,
+        definitions: Res<Assets<CharacterAnimations>>,
+        mut query: Query<(
+            &AnimationSource,
+            ChangeTrackers<AnimationSource>,
+            &mut Animator,
+            &mut Handle<TextureAtlas>,
+            &mut TextureAtlasSprite,
+        )>,
+    ) {
+        let updated: HashSet<_> = asset_events
+            .iter()
+            .filter_map(|event| match event {
+                AssetEvent::Created { handle } | AssetEvent::Modified { handle } => Some(handle),
+                AssetEvent::Removed { .. } => None,
+            })
+            .collect();
+
+        for (source, tracker, mut animator, mut atlas, mut sprite) in query.iter_mut() {
+            if !tracker.is_changed() && !updated.contains(&source.sheet.definition) {
+                continue;
+            }
+            let definition = match definitions.get(&source.sheet.definition) {
+                Some(definition) => definition,
+                None => continue,
+            };
+
+            *atlas = source.sheet.atlas.clone();
+            animator.set_animations(definition.build(&source.sheet), &definition.initial);
+            if let Some((x, y)) = definition.anchor {
+                sprite.anchor = Anchor::Custom(Vec2::new(x, y));
+            }
+        }
+    }
+}
@@ -0,0 +1,452 @@
+use super::Animator;
+use crate::{AppState, SpriteEffects};
+use bevy::{prelude::*, utils::HashSet};
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+use bevy_rapier2d::prelude::*;
+
+const TIME_STEP: f32 = 1. / 60.;
+
+/// Horizontal speed, in pixels per second, above which a character walks.
+const WALK_SPEED: f32 = 5.;
+
+/// Vertical speed above which a character leaving the ground jumps or falls.
+const AIR_SPEED: f32 = 20.;
+
+/// Vertical component of a contact normal under which the contact is ground.
+const GROUND_NORMAL: f32 = 0.5;
+
+/// States of an `AnimationGraph`, each played with the animation of the same name.
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Walk,
+    Jump,
+    Fall,
+    Attack,
+    Hurt,
+    Die,
+}
+impl AnimationState {
+    pub fn animation(&self) -> &'static str {
+        match self {
+            AnimationState::Idle => "idle",
+            AnimationState::Walk => "walk",
+            AnimationState::Jump => "jump",
+            AnimationState::Fall => "fall",
+            AnimationState::Attack => "attack",
+            AnimationState::Hurt => "hurt",
+            AnimationState::Die => "death",
+        }
+    }
+
+    /// Whether any transition may leave the state before its animation is over.
+    fn interruptible(&self) -> bool {
+        !matches!(
+            self,
+            AnimationState::Attack | AnimationState::Hurt | AnimationState::Die
+        )
+    }
+}
+
+/// One-shot events of the gameplay, consumed by the next step of the graph.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Trigger {
+    Attack,
+    Hurt,
+}
+
+/// What the graph knows of its holder, filled by the gameplay and `AnimationParams::track`.
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component, Default)]
+pub struct AnimationParams {
+    /// Pixels per second, measured from the moves of the holder.
+    velocity: Vec2,
+    grounded: bool,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    was_grounded: bool,
+    facing_left: bool,
+    dead: bool,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    triggers: HashSet<Trigger>,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    last_position: Option<Vec2>,
+}
+impl AnimationParams {
+    pub fn trigger(&mut self, trigger: Trigger) {
+        self.triggers.insert(trigger);
+    }
+
+    /// Turns the holder toward the sign of `direction`, keeping its side if zero.
+    pub fn face(&mut self, direction: f32) {
+        if direction != 0. {
+            self.facing_left = direction < 0.;
+        }
+    }
+
+    pub fn kill(&mut self) {
+        self.dead = true;
+    }
+
+    /// Whether the holder touched the ground on the last step.
+    pub fn landed(&self) -> bool {
+        self.grounded && !self.was_grounded
+    }
+
+    /// Whether the holder left the ground upward on the last step.
+    pub fn jumped(&self) -> bool {
+        !self.grounded && self.was_grounded && self.velocity.y > 0.
+    }
+
+    /// Measures the velocity of the holder and whether it stands on something.
+    fn track(
+        rapier_context: Res<RapierContext>,
+        mut query: Query<(Entity, &Transform, &mut AnimationParams)>,
+    ) {
+        for (entity, transform, mut params) in query.iter_mut() {
+            let position = transform.translation.truncate();
+            params.velocity = params
+                .last_position
+                .map_or(Vec2::ZERO, |last| (position - last) / TIME_STEP);
+            params.last_position = Some(position);
+
+            params.was_grounded = params.grounded;
+            params.grounded = rapier_context
+                .contacts_with(entity)
+                .filter(|pair| pair.has_any_active_contacts())
+                .any(|pair| {
+                    // Normals point from the first collider of the pair toward the second.
+                    let down = if pair.collider1() == entity { -1. } else { 1. };
+                    pair.manifolds()
+                        .any(|manifold| manifold.normal().y * down > GROUND_NORMAL)
+                });
+        }
+    }
+}
+
+/// Condition on the `AnimationParams` for a transition to be taken.
+#[derive(Clone, Copy, Debug)]
+pub enum Condition {
+    /// Horizontal speed of at least the given pixels per second.
+    Moving(f32),
+    /// Horizontal speed under the given pixels per second.
+    Still(f32),
+    /// Upward speed above the given pixels per second.
+    Rising(f32),
+    /// Downward speed above the given pixels per second.
+    Falling(f32),
+    Grounded,
+    Airborne,
+    Triggered(Trigger),
+    Dead,
+}
+impl Condition {
+    fn holds(&self, params: &AnimationParams) -> bool {
+        match *self {
+            Condition::Moving(speed) => params.velocity.x.abs() >= speed,
+            Condition::Still(speed) => params.velocity.x.abs() < speed,
+            Condition::Rising(speed) => params.velocity.y > speed,
+            Condition::Falling(speed) => params.velocity.y < -speed,
+            Condition::Grounded => params.grounded,
+            Condition::Airborne => !params.grounded,
+            Condition::Triggered(trigger) => params.triggers.contains(&trigger),
+            Condition::Dead => params.dead,
+        }
+    }
+}
+
+/// Move to a state once all the conditions hold, from any state unless restricted with `after`.
+#[derive(Clone, Debug)]
+pub struct Transition {
+    from: Option<AnimationState>,
+    to: AnimationState,
+    conditions: Vec<Condition>,
+    /// Transitions of a higher priority are tried first, and can interrupt lower ones.
+    priority: u8,
+}
+impl Transition {
+    pub fn to(state: AnimationState) -> Self {
+        Transition {
+            from: None,
+            to: state,
+            conditions: vec![],
+            priority: 0,
+        }
+    }
+
+    pub fn after(mut self, state: AnimationState) -> Self {
+        self.from = Some(state);
+        self
+    }
+
+    pub fn when(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+}
+
+/// Picks the animation of the holder's `Animator` from its `AnimationParams`.
+///
+/// States whose animation the animator lacks are never entered. Attack, hurt and die play
+/// through unless a transition of a higher priority comes, and nothing leaves die.
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct AnimationGraph {
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    transitions: Vec<Transition>,
+    current: AnimationState,
+    /// Priority of the transition which entered the current state.
+    priority: u8,
+}
+impl Default for AnimationGraph {
+    fn default() -> Self {
+        AnimationGraph::character()
+    }
+}
+impl AnimationGraph {
+    pub fn new() -> Self {
+        AnimationGraph {
+            transitions: vec![],
+            current: AnimationState::Idle,
+            priority: 0,
+        }
+    }
+
+    pub fn with_transition(mut self, transition: Transition) -> Self {
+        let index = self
+            .transitions
+            .partition_point(|other| other.priority >= transition.priority);
+        self.transitions.insert(index, transition);
+        self
+    }
+
+    /// Graph shared by the player, monsters and bosses.
+    pub fn character() -> Self {
+        use AnimationState::*;
+
+        AnimationGraph::new()
+            .with_transition(Transition::to(Die).when(Condition::Dead).with_priority(3))
+            .with_transition(
+                Transition::to(Hurt)
+                    .when(Condition::Triggered(Trigger::Hurt))
+                    .with_priority(2),
+            )
+            .with_transition(
+                Transition::to(Attack)
+                    .when(Condition::Triggered(Trigger::Attack))
+                    .with_priority(1),
+            )
+            .with_transition(
+                Transition::to(Fall)
+                    .after(Jump)
+                    .when(Condition::Airborne)
+                    .when(Condition::Falling(0.)),
+            )
+            .with_transition(Transition::to(Idle).after(Fall).when(Condition::Grounded))
+            .with_transition(
+                Transition::to(Jump)
+                    .when(Condition::Airborne)
+                    .when(Condition::Rising(AIR_SPEED)),
+            )
+            .with_transition(
+                Transition::to(Fall)
+                    .when(Condition::Airborne)
+                    .when(Condition::Falling(AIR_SPEED)),
+            )
+            .with_transition(Transition::to(Walk).when(Condition::Moving(WALK_SPEED)))
+            .with_transition(Transition::to(Idle).when(Condition::Still(WALK_SPEED)))
+    }
+
+    pub fn state(&self) -> AnimationState {
+        self.current
+    }
+
+    fn next(&self, params: &AnimationParams, animator: &Animator) -> Option<&Transition> {
+        if self.current == AnimationState::Die {
+            return None;
+        }
+        let finished = animator.is_finished() || !animator.is_playing(self.current.animation());
+
+        self.transitions.iter().find(|transition| {
+            transition.from.is_none_or(|from| from == self.current)
+                && (self.current.interruptible() || finished || transition.priority > self.priority)
+                && animator.has_animation(transition.to.animation())
+                && transition
+                    .conditions
+                    .iter()
+                    .all(|condition| condition.holds(params))
+        })
+    }
+
+    fn transition(mut query: Query<(&mut AnimationGraph, &mut AnimationParams, &mut Animator)>) {
+        for (mut graph, mut params, mut animator) in query.iter_mut() {
+            let next = graph
+                .next(&params, &animator)
+                .map(|transition| (transition.to, transition.priority));
+            params.triggers.clear();
+
+            match next {
+                Some((state, priority)) if state != graph.current => {
+                    graph.current = state;
+                    graph.priority = priority;
+                    animator.change_animation(state.animation(), params.facing_left);
+                }
+                // Keeps on with the state, only turning around while it can be interrupted.
+                _ if graph.current.interruptible()
+                    && animator.has_animation(graph.current.animation()) =>
+                {
+                    animator.change_animation(graph.current.animation(), params.facing_left);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::new()
+            .with_run_criteria(AppState::fixed_step(TIME_STEP))
+            .with_system(AnimationParams::track)
+            .with_system(AnimationGraph::transition.after(AnimationParams::track))
+            .with_system(SpriteEffects::squash_and_stretch.after(AnimationParams::track))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Animation, Repeat};
+    use AnimationState::*;
+
+    /// Character without jump and fall animations, run through one step of its graph at a time.
+    struct Character {
+        world: World,
+        stage: SystemStage,
+        entity: Entity,
+    }
+    impl Character {
+        fn new() -> Self {
+            let animation = |repeat| Animation::new(vec![0, 1], 0.1).with_repeat(repeat);
+            let animator = Animator::new(vec![
+                ("idle", animation(Repeat::Loop)),
+                ("walk", animation(Repeat::Loop)),
+                ("attack", animation(Repeat::Once)),
+                ("hurt", animation(Repeat::Once)),
+                ("death", animation(Repeat::HoldLast)),
+            ]);
+            let mut world = World::new();
+            let entity = world
+                .spawn()
+                .insert(AnimationGraph::character())
+                .insert(AnimationParams {
+                    grounded: true,
+                    ..Default::default()
+                })
+                .insert(animator)
+                .id();
+            let stage = SystemStage::single_threaded().with_system(AnimationGraph::transition);
+            Character {
+                world,
+                stage,
+                entity,
+            }
+        }
+
+        fn params(&mut self) -> Mut<'_, AnimationParams> {
+            self.world.get_mut::<AnimationParams>(self.entity).unwrap()
+        }
+
+        /// Runs a step of the graph, returning the state it is left in.
+        fn step(&mut self) -> AnimationState {
+            self.stage.run(&mut self.world);
+            let graph = self.world.get::<AnimationGraph>(self.entity).unwrap();
+            let animator = self.world.get::<Animator>(self.entity).unwrap();
+            assert!(animator.is_playing(graph.state().animation()));
+            graph.state()
+        }
+
+        /// Ends the animation being played, as `Animator::animate` would.
+        fn finish_animation(&mut self) {
+            self.world
+                .get_mut::<Animator>(self.entity)
+                .unwrap()
+                .finished = true;
+        }
+    }
+
+    #[test]
+    fn sorts_transitions_by_priority() {
+        let graph = AnimationGraph::new()
+            .with_transition(Transition::to(Idle))
+            .with_transition(Transition::to(Die).with_priority(3))
+            .with_transition(Transition::to(Walk))
+            .with_transition(Transition::to(Hurt).with_priority(2));
+        let order: Vec<_> = graph.transitions.iter().map(|t| t.to).collect();
+        assert_eq!(order, [Die, Hurt, Idle, Walk]);
+    }
+
+    #[test]
+    fn walks_while_moving() {
+        let mut character = Character::new();
+        assert_eq!(character.step(), Idle);
+        character.params().velocity.x = -WALK_SPEED;
+        assert_eq!(character.step(), Walk);
+        character.params().velocity.x = 0.;
+        assert_eq!(character.step(), Idle);
+    }
+
+    #[test]
+    fn never_enters_states_without_animation() {
+        let mut character = Character::new();
+        character.params().grounded = false;
+        character.params().velocity.y = AIR_SPEED * 2.;
+        assert_eq!(character.step(), Idle);
+    }
+
+    #[test]
+    fn plays_attacks_through() {
+        let mut character = Character::new();
+        character.params().trigger(Trigger::Attack);
+        assert_eq!(character.step(), Attack);
+        assert!(character.params().triggers.is_empty(), "trigger kept");
+
+        character.params().velocity.x = WALK_SPEED;
+        assert_eq!(character.step(), Attack);
+        character.finish_animation();
+        assert_eq!(character.step(), Walk);
+    }
+
+    #[test]
+    fn higher_priorities_interrupt() {
+        let mut character = Character::new();
+        character.params().trigger(Trigger::Attack);
+        assert_eq!(character.step(), Attack);
+
+        character.params().trigger(Trigger::Hurt);
+        assert_eq!(character.step(), Hurt);
+        character.params().trigger(Trigger::Attack);
+        assert_eq!(character.step(), Hurt);
+
+        character.params().trigger(Trigger::Hurt);
+        character.params().kill();
+        assert_eq!(character.step(), Die);
+    }
+
+    #[test]
+    fn stays_dead() {
+        let mut character = Character::new();
+        character.params().kill();
+        assert_eq!(character.step(), Die);
+
+        character.finish_animation();
+        character.params().dead = false;
+        character.params().trigger(Trigger::Hurt);
+        assert_eq!(character.step(), Die);
+    }
+}
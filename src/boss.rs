@@ -0,0 +1,421 @@
+mod dungeon_master;
+mod goblin_king;
+mod projectile;
+mod skeleton_king;
+mod slime_king;
+
+use crate::{
+    dungeon::{Dungeon, NavObstacle},
+    Action, ActionState, Animation, AnimationGraph, AnimationParams, AnimationSource, Animator,
+    AppState, Damage, DeathAnimation, DeathEvent, Dying, Health, Player, SpriteEffects,
+    SpriteSheet, FONT, FRAME_DURATION,
+};
+use bevy::prelude::*;
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+use bevy_rapier2d::prelude::*;
+pub use dungeon_master::*;
+pub use goblin_king::*;
+pub use projectile::*;
+pub use skeleton_king::*;
+pub use slime_king::*;
+
+const SCALE: f32 = 0.3;
+
+const HALF_SIZE: f32 = 64. * SCALE;
+
+const CONTACT_RANGE: f32 = 24.;
+
+const OBSTACLE_HALF_SIZE: f32 = 48.;
+
+/// Tint of a boss past its enrage threshold.
+const ENRAGED_TINT: Color = Color::rgb(1., 0.55, 0.55);
+
+const DOOR_SCALE: f32 = 0.3;
+
+/// Distance under which the player can go through an unlocked arena door.
+const DOOR_RANGE: f32 = 24.;
+
+const CLOSED_ANIMATION: &str = "close";
+
+const OPEN_ANIMATION: &str = "open";
+
+const IDLE_ANIMATION: &str = "idle";
+
+const BREAK_ANIMATION: &str = "break";
+
+/// Bosses, their arenas and the victory over the last one.
+///
+/// Needs the `HealthPlugin`, the `AnimationPlugin` and the `DungeonPlugin`.
+pub struct BossPlugin;
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BulletPatterns>()
+            .add_system_set(Boss::system_set())
+            .add_system_set(SlimeKing::system_set())
+            .add_system_set(GoblinKing::system_set())
+            .add_system_set(SkeletonKing::system_set())
+            .add_system_set(DungeonMaster::system_set())
+            .add_system_set(DungeonMaster::victory_system_set());
+    }
+}
+
+pub struct BossAtlases {
+    pub slime_king: SpriteSheet,
+    pub slime_door: SpriteSheet,
+    pub goblin_king: SpriteSheet,
+    pub goblin_king_projectile: SpriteSheet,
+    pub goblin_door: SpriteSheet,
+    pub skeleton_king: SpriteSheet,
+    pub skeleton_door: SpriteSheet,
+    pub dungeon_master: SpriteSheet,
+    pub dungeon_master_projectiles: Vec<SpriteSheet>,
+    pub dungeon_master_door: SpriteSheet,
+}
+
+#[derive(Bundle)]
+pub struct BossBundle {
+    #[bundle]
+    sprite_bundle: SpriteSheetBundle,
+    boss: Boss,
+    animator: Animator,
+    animation_source: AnimationSource,
+    animation_graph: AnimationGraph,
+    animation_params: AnimationParams,
+    effects: SpriteEffects,
+    health: Health,
+    damage: Damage,
+    collider: Collider,
+    rigidbody: RigidBody,
+    velocity: Velocity,
+    locked_axis: LockedAxes,
+}
+impl BossBundle {
+    pub fn new(sheet: &SpriteSheet, health: f32, damage: f32, at: Vec3) -> Self {
+        BossBundle {
+            sprite_bundle: SpriteSheetBundle {
+                texture_atlas: sheet.atlas.clone(),
+                transform: Transform {
+                    translation: at,
+                    scale: Vec3::new(SCALE, SCALE, 1.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            boss: Boss::default(),
+            animator: Animator::default(),
+            animation_source: AnimationSource::new(sheet),
+            animation_graph: AnimationGraph::character(),
+            animation_params: AnimationParams::default(),
+            effects: SpriteEffects::default(),
+            health: Health::new(health),
+            damage: Damage {
+                amount: damage,
+                range: CONTACT_RANGE,
+            },
+            collider: Collider::cuboid(64., 64.),
+            rigidbody: RigidBody::Dynamic,
+            velocity: Velocity::default(),
+            locked_axis: LockedAxes::ROTATION_LOCKED,
+        }
+    }
+}
+
+/// Guardian of the arena at the end of a floor, asleep until the player steps in.
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component, Default)]
+pub struct Boss {
+    active: bool,
+}
+impl Boss {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Spawns the boss guarding the arena of floor `depth`, behind its arena door.
+    pub fn spawn_for_floor(commands: &mut Commands, atlases: &BossAtlases, depth: usize) {
+        let (min, max) = Dungeon::arena();
+        let at = Vec3::new((min.x + max.x) / 2., min.y + 32., 0.);
+
+        let door = match depth {
+            1 => {
+                SlimeKing::spawn(commands, atlases, at);
+                &atlases.slime_door
+            }
+            2 => {
+                GoblinKing::spawn(commands, atlases, at);
+                &atlases.goblin_door
+            }
+            3 => {
+                SkeletonKing::spawn(commands, atlases, at);
+                &atlases.skeleton_door
+            }
+            _ => {
+                DungeonMaster::spawn(commands, atlases, at);
+                &atlases.dungeon_master_door
+            }
+        };
+
+        commands
+            .spawn_bundle(ArenaDoorBundle::new(door, Dungeon::arena_door().extend(0.)))
+            .insert(Name::new("Arena Door"));
+    }
+
+    fn activate(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        player: Query<&Transform, With<Player>>,
+        mut bosses: Query<(&mut Boss, &Name), Without<Dying>>,
+    ) {
+        let player = match player.get_single() {
+            Ok(transform) => transform.translation.truncate(),
+            Err(_) => return,
+        };
+        let (min, max) = Dungeon::arena();
+        if player.x < min.x + HALF_SIZE || player.x > max.x || player.y > max.y {
+            return;
+        }
+
+        for (mut boss, name) in bosses.iter_mut() {
+            if !boss.active {
+                boss.active = true;
+                BossHealthBar::spawn(&mut commands, &asset_server, name.as_str());
+            }
+        }
+    }
+
+    fn update_health_bar(
+        bosses: Query<&Health, With<Boss>>,
+        mut fills: Query<&mut Style, With<BossHealthBarFill>>,
+    ) {
+        let health = match bosses.iter().next() {
+            Some(health) => health,
+            None => return,
+        };
+        for mut style in fills.iter_mut() {
+            style.size.width = Val::Percent(health.fraction() * 100.);
+        }
+    }
+
+    fn defeat(
+        mut commands: Commands,
+        mut death_events: EventReader<DeathEvent>,
+        bosses: Query<Option<&DungeonMaster>, With<Boss>>,
+        mut doors: Query<(Entity, &mut ArenaDoor, &mut Animator)>,
+        bars: Query<Entity, With<BossHealthBar>>,
+    ) {
+        for DeathEvent { entity } in death_events.iter() {
+            let last_boss = match bosses.get(*entity) {
+                Ok(master) => master.is_some(),
+                Err(_) => continue,
+            };
+
+            // The run ends with the last boss, so there is no floor to descend to.
+            if !last_boss {
+                for (door, mut arena_door, mut animator) in doors.iter_mut() {
+                    arena_door.locked = false;
+                    animator.change_animation(OPEN_ANIMATION, false);
+                    commands.entity(door).remove::<Collider>();
+                }
+            }
+            for bar in bars.iter() {
+                commands.entity(bar).despawn_recursive();
+            }
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::on_update(AppState::RunningGame)
+            .with_system(Boss::activate)
+            .with_system(Boss::update_health_bar)
+            .with_system(Boss::defeat)
+            .with_system(ArenaDoor::enter)
+            .with_system(Projectile::travel)
+            .with_system(Projectile::despawn)
+    }
+}
+
+#[derive(Bundle)]
+pub struct BossObstacleBundle {
+    #[bundle]
+    sprite_bundle: SpriteSheetBundle,
+    obstacle: BossObstacle,
+    nav_obstacle: NavObstacle,
+    animator: Animator,
+    effects: SpriteEffects,
+    health: Health,
+    death_animation: DeathAnimation,
+    collider: Collider,
+    rigidbody: RigidBody,
+}
+impl BossObstacleBundle {
+    /// Obstacle playing the `animation` of the sheet, then `death_animation` once broken.
+    pub fn new(
+        sheet: &SpriteSheet,
+        animation: &str,
+        death_animation: &str,
+        health: f32,
+        at: Vec3,
+    ) -> Self {
+        BossObstacleBundle {
+            sprite_bundle: SpriteSheetBundle {
+                texture_atlas: sheet.atlas.clone(),
+                transform: Transform {
+                    translation: at,
+                    scale: Vec3::new(SCALE, SCALE, 1.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            obstacle: BossObstacle,
+            nav_obstacle: NavObstacle {
+                half_size: Vec2::splat(OBSTACLE_HALF_SIZE * SCALE),
+            },
+            animator: Animator::new(vec![
+                (IDLE_ANIMATION, sheet.animation(animation)),
+                (
+                    BREAK_ANIMATION,
+                    sheet.animation(death_animation).hold_last(),
+                ),
+            ]),
+            effects: SpriteEffects::default(),
+            health: Health::new(health),
+            death_animation: DeathAnimation(BREAK_ANIMATION),
+            collider: Collider::cuboid(OBSTACLE_HALF_SIZE, OBSTACLE_HALF_SIZE),
+            rigidbody: RigidBody::Fixed,
+        }
+    }
+}
+
+/// Destructible obstacle summoned by a boss.
+#[derive(Component)]
+pub struct BossObstacle;
+
+#[derive(Bundle)]
+pub struct ArenaDoorBundle {
+    #[bundle]
+    sprite_bundle: SpriteSheetBundle,
+    door: ArenaDoor,
+    animator: Animator,
+    collider: Collider,
+    rigidbody: RigidBody,
+}
+impl ArenaDoorBundle {
+    /// Door shown on the last frame of its closing animation until it opens.
+    pub fn new(sheet: &SpriteSheet, at: Vec3) -> Self {
+        let closed = sheet
+            .frames(CLOSED_ANIMATION)
+            .last()
+            .copied()
+            .unwrap_or_default();
+        ArenaDoorBundle {
+            sprite_bundle: SpriteSheetBundle {
+                texture_atlas: sheet.atlas.clone(),
+                sprite: TextureAtlasSprite {
+                    index: closed,
+                    ..Default::default()
+                },
+                transform: Transform {
+                    translation: at,
+                    scale: Vec3::new(DOOR_SCALE, DOOR_SCALE, 1.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            door: ArenaDoor { locked: true },
+            animator: Animator::new(vec![
+                (
+                    CLOSED_ANIMATION,
+                    Animation::new(vec![closed], FRAME_DURATION),
+                ),
+                (OPEN_ANIMATION, sheet.animation(OPEN_ANIMATION).hold_last()),
+            ]),
+            collider: Collider::cuboid(8. / DOOR_SCALE, 24. / DOOR_SCALE),
+            rigidbody: RigidBody::Fixed,
+        }
+    }
+}
+
+/// Exit of a boss arena, locked until the boss dies.
+#[derive(Component)]
+pub struct ArenaDoor {
+    locked: bool,
+}
+impl ArenaDoor {
+    fn enter(
+        mut action_state: ResMut<ActionState>,
+        mut state: ResMut<State<AppState>>,
+        player: Query<&Transform, (With<Player>, Without<Dying>)>,
+        doors: Query<(&ArenaDoor, &Transform)>,
+    ) {
+        let player = match player.get_single() {
+            Ok(transform) => transform.translation.truncate(),
+            Err(_) => return,
+        };
+
+        let near_open_door = doors.iter().any(|(door, transform)| {
+            !door.locked && transform.translation.truncate().distance(player) <= DOOR_RANGE
+        });
+        if near_open_door && action_state.consume(Action::Interact) {
+            let _ = state.set(AppState::Descending);
+        }
+    }
+}
+
+/// Health bar of the active boss, shown at the bottom of the screen.
+#[derive(Component)]
+pub struct BossHealthBar;
+impl BossHealthBar {
+    fn spawn(commands: &mut Commands, asset_server: &AssetServer, name: &str) {
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Percent(25.),
+                        bottom: Val::Px(32.),
+                        ..Default::default()
+                    },
+                    size: Size::new(Val::Percent(50.), Val::Px(16.)),
+                    ..Default::default()
+                },
+                color: Color::rgba(0., 0., 0., 0.7).into(),
+                ..Default::default()
+            })
+            .insert(BossHealthBar)
+            .with_children(|bar| {
+                bar.spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                        ..Default::default()
+                    },
+                    color: Color::rgb(0.8, 0.1, 0.1).into(),
+                    ..Default::default()
+                })
+                .insert(BossHealthBarFill);
+                bar.spawn_bundle(TextBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            bottom: Val::Px(20.),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    text: Text::with_section(
+                        name,
+                        TextStyle {
+                            font: asset_server.load(FONT),
+                            font_size: 24.,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                });
+            });
+    }
+}
+
+#[derive(Component)]
+pub struct BossHealthBarFill;
@@ -0,0 +1,263 @@
+use super::{Boss, BossAtlases, BossBundle, ProjectileBundle, HALF_SIZE};
+use crate::{
+    dungeon::Dungeon, AnimationParams, AppState, DeathDuration, DeathEvent, Dying, Health, Player,
+    RunEnd, ScreenShakeEvent,
+};
+use bevy::prelude::*;
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+use std::time::Duration;
+
+const PATTERNS: &str = include_str!("../../assets/data/bullet_patterns.ron");
+
+const TIME_STEP: f32 = 1. / 60.;
+
+const HEALTH: f32 = 40.;
+
+const DAMAGE: f32 = 3.;
+
+const WALK_SPEED: f32 = 15.;
+
+/// Seconds taken by the 12 frames of the death animation.
+const DEATH_DURATION: f32 = 1.5;
+
+/// Seconds between the death of the Dungeon Master and the victory screen.
+const VICTORY_DELAY: f32 = 2.5;
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum Shot {
+    /// A single projectile toward the player.
+    Aimed,
+    /// `count` projectiles evenly spread over `angle` radians, centered on the player.
+    Spread { count: usize, angle: f32 },
+    /// `count` slow orbs fanning out then turning toward the player.
+    Homing { count: usize, turn_rate: f32 },
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct BulletPattern {
+    shot: Shot,
+    /// Index of the Dungeon Master projectile sprites.
+    projectile: usize,
+    speed: f32,
+    damage: f32,
+    /// Number of times the shot is fired, `interval` seconds apart.
+    repeat: usize,
+    interval: f32,
+    /// Seconds waited before the next pattern.
+    cooldown: f32,
+}
+
+#[derive(Deserialize)]
+struct Phase {
+    health: f32,
+    patterns: Vec<BulletPattern>,
+}
+
+/// Patterns of the Dungeon Master per phase, read from `assets/data/bullet_patterns.ron`.
+pub struct BulletPatterns {
+    phases: Vec<Phase>,
+}
+impl Default for BulletPatterns {
+    fn default() -> Self {
+        BulletPatterns {
+            phases: ron::from_str(PATTERNS).expect("Invalid bullet patterns"),
+        }
+    }
+}
+impl BulletPatterns {
+    /// Last phase whose health threshold is reached at `fraction` of the boss health.
+    fn phase(&self, fraction: f32) -> usize {
+        self.phases
+            .iter()
+            .rposition(|phase| fraction <= phase.health)
+            .unwrap_or(0)
+    }
+
+    fn get(&self, phase: usize, pattern: usize) -> Option<&BulletPattern> {
+        let patterns = &self.phases.get(phase)?.patterns;
+        patterns.get(pattern % patterns.len().max(1))
+    }
+}
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct DungeonMaster {
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    phase: usize,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    pattern: usize,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    shots: usize,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    timer: Timer,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    direction: f32,
+}
+impl Default for DungeonMaster {
+    fn default() -> Self {
+        DungeonMaster {
+            phase: 0,
+            pattern: 0,
+            shots: 0,
+            timer: Timer::from_seconds(1., false),
+            direction: 1.,
+        }
+    }
+}
+impl DungeonMaster {
+    pub fn spawn(commands: &mut Commands, atlases: &BossAtlases, at: Vec3) {
+        commands
+            .spawn_bundle(BossBundle::new(&atlases.dungeon_master, HEALTH, DAMAGE, at))
+            .insert(DeathDuration(DEATH_DURATION))
+            .insert(DungeonMaster::default())
+            .insert(Name::new("Dungeon Master"));
+    }
+
+    /// Paces the arena from wall to wall.
+    fn act(
+        mut query: Query<
+            (
+                &mut DungeonMaster,
+                &Boss,
+                &Transform,
+                &mut Velocity,
+                &mut AnimationParams,
+            ),
+            Without<Dying>,
+        >,
+    ) {
+        let (min, max) = Dungeon::arena();
+
+        for (mut master, boss, transform, mut velocity, mut params) in query.iter_mut() {
+            if !boss.is_active() {
+                velocity.linvel.x = 0.;
+                continue;
+            }
+
+            let x = transform.translation.x;
+            if x < min.x + 2. * HALF_SIZE {
+                master.direction = 1.;
+            } else if x > max.x - 2. * HALF_SIZE {
+                master.direction = -1.;
+            }
+            velocity.linvel.x = master.direction * WALK_SPEED;
+            params.face(master.direction);
+        }
+    }
+
+    fn cast(
+        mut commands: Commands,
+        atlases: Res<BossAtlases>,
+        patterns: Res<BulletPatterns>,
+        mut shake_events: EventWriter<ScreenShakeEvent>,
+        player: Query<&Transform, With<Player>>,
+        mut query: Query<(&mut DungeonMaster, &Boss, &Health, &Transform), Without<Dying>>,
+    ) {
+        let player = match player.get_single() {
+            Ok(transform) => transform.translation.truncate(),
+            Err(_) => return,
+        };
+
+        for (mut master, boss, health, transform) in query.iter_mut() {
+            if !boss.is_active() {
+                continue;
+            }
+
+            let phase = patterns.phase(health.fraction());
+            if phase != master.phase {
+                master.phase = phase;
+                master.pattern = 0;
+                master.shots = 0;
+                shake_events.send(ScreenShakeEvent {
+                    intensity: 4.,
+                    duration: 0.4,
+                });
+            }
+
+            if !master
+                .timer
+                .tick(Duration::from_secs_f32(TIME_STEP))
+                .finished()
+            {
+                continue;
+            }
+            let pattern = match patterns.get(master.phase, master.pattern) {
+                Some(pattern) => *pattern,
+                None => continue,
+            };
+
+            let origin = transform.translation.truncate() + Vec2::new(0., HALF_SIZE);
+            let aim = (player - origin).normalize_or_zero();
+            let atlas = &atlases.dungeon_master_projectiles[pattern
+                .projectile
+                .min(atlases.dungeon_master_projectiles.len() - 1)];
+            let (angles, turn_rate) = match pattern.shot {
+                Shot::Aimed => (vec![0.], 0.),
+                Shot::Spread { count, angle } => (spread(count, angle), 0.),
+                Shot::Homing { count, turn_rate } => (spread(count, 1.5), turn_rate),
+            };
+            for angle in angles {
+                let (sin, cos) = angle.sin_cos();
+                let direction = Vec2::new(aim.x * cos - aim.y * sin, aim.x * sin + aim.y * cos);
+                commands
+                    .spawn_bundle(
+                        ProjectileBundle::new(
+                            atlas,
+                            direction * pattern.speed,
+                            pattern.damage,
+                            origin.extend(1.),
+                        )
+                        .with_homing(turn_rate),
+                    )
+                    .insert(Name::new("Dungeon Master Projectile"));
+            }
+
+            master.shots += 1;
+            if master.shots < pattern.repeat {
+                master.timer = Timer::from_seconds(pattern.interval, false);
+            } else {
+                master.shots = 0;
+                master.pattern += 1;
+                master.timer = Timer::from_seconds(pattern.cooldown, false);
+            }
+        }
+    }
+
+    /// Ends the run once the death animation of the Dungeon Master is over.
+    fn victory(
+        mut commands: Commands,
+        mut death_events: EventReader<DeathEvent>,
+        run_end: Option<Res<RunEnd>>,
+        masters: Query<(), With<DungeonMaster>>,
+    ) {
+        for DeathEvent { entity } in death_events.iter() {
+            if masters.get(*entity).is_ok() && run_end.is_none() {
+                commands.insert_resource(RunEnd::after(AppState::Victory, VICTORY_DELAY));
+            }
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::new()
+            .with_run_criteria(AppState::fixed_step(TIME_STEP))
+            .with_system(DungeonMaster::act)
+            .with_system(DungeonMaster::cast)
+    }
+
+    pub fn victory_system_set() -> SystemSet {
+        SystemSet::on_update(AppState::RunningGame).with_system(DungeonMaster::victory)
+    }
+}
+
+/// Angles of `count` projectiles evenly spread over `angle` radians.
+fn spread(count: usize, angle: f32) -> Vec<f32> {
+    if count <= 1 {
+        return vec![0.];
+    }
+    (0..count)
+        .map(|i| -angle / 2. + angle * i as f32 / (count - 1) as f32)
+        .collect()
+}
@@ -0,0 +1,197 @@
+use super::{Boss, BossAtlases, BossBundle, ProjectileBundle, HALF_SIZE};
+use crate::{
+    AnimationParams, AppState, Dying, Health, MonsterArchetypes, MonsterAtlases, MonsterBundle,
+    Player, ScreenShakeEvent, Species,
+};
+use bevy::prelude::*;
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+use bevy_rapier2d::prelude::*;
+use std::time::Duration;
+
+const TIME_STEP: f32 = 1. / 60.;
+
+const HEALTH: f32 = 24.;
+
+const DAMAGE: f32 = 2.;
+
+const WALK_SPEED: f32 = 25.;
+
+/// Distance the Goblin King tries to keep from the player while shooting.
+const PREFERRED_DISTANCE: f32 = 96.;
+
+const PROJECTILE_SPEED: f32 = 90.;
+
+const PROJECTILE_DAMAGE: f32 = 1.5;
+
+/// Health fractions under which the Goblin King enters its next phase.
+const PHASE_THRESHOLDS: [f32; 2] = [0.66, 0.33];
+
+/// Seconds between barrages, per phase.
+const BARRAGE_INTERVALS: [f32; 3] = [1.6, 2., 1.2];
+
+const MINION_INTERVAL: f32 = 8.;
+
+const MAX_MINIONS: usize = 3;
+
+/// Regular Goblin called to the fight by the Goblin King.
+#[derive(Component)]
+pub struct GoblinMinion;
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct GoblinKing {
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    phase: usize,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    volley: usize,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    barrage: Timer,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    minions: Timer,
+}
+impl Default for GoblinKing {
+    fn default() -> Self {
+        GoblinKing {
+            phase: 0,
+            volley: 0,
+            barrage: Timer::from_seconds(BARRAGE_INTERVALS[0], false),
+            minions: Timer::from_seconds(MINION_INTERVAL, false),
+        }
+    }
+}
+impl GoblinKing {
+    pub fn spawn(commands: &mut Commands, atlases: &BossAtlases, at: Vec3) {
+        commands
+            .spawn_bundle(BossBundle::new(&atlases.goblin_king, HEALTH, DAMAGE, at))
+            .insert(GoblinKing::default())
+            .insert(Name::new("Goblin King"));
+    }
+
+    /// Angles, relative to the direction of the player, of the projectiles of a barrage.
+    fn barrage(&self) -> &'static [f32] {
+        match (self.phase, self.volley % 2) {
+            (0, _) => &[0.],
+            (1, _) => &[-0.3, 0., 0.3],
+            (_, 0) => &[-0.6, -0.3, 0., 0.3, 0.6],
+            (_, _) => &[-0.1, 0., 0.1],
+        }
+    }
+
+    fn act(
+        player: Query<&Transform, With<Player>>,
+        mut query: Query<
+            (&Boss, &Transform, &mut Velocity, &mut AnimationParams),
+            (With<GoblinKing>, Without<Dying>),
+        >,
+    ) {
+        let player = player.get_single().ok().map(|t| t.translation.truncate());
+
+        for (boss, transform, mut velocity, mut params) in query.iter_mut() {
+            let player = match player {
+                Some(player) if boss.is_active() => player,
+                _ => {
+                    velocity.linvel.x = 0.;
+                    continue;
+                }
+            };
+
+            let offset = player.x - transform.translation.x;
+            let direction = offset.signum();
+            velocity.linvel.x = if offset.abs() > PREFERRED_DISTANCE + 16. {
+                direction * WALK_SPEED
+            } else if offset.abs() < PREFERRED_DISTANCE - 16. {
+                -direction * WALK_SPEED
+            } else {
+                0.
+            };
+
+            params.face(direction);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn attack(
+        mut commands: Commands,
+        boss_atlases: Res<BossAtlases>,
+        archetypes: Res<MonsterArchetypes>,
+        monster_atlases: Res<MonsterAtlases>,
+        mut shake_events: EventWriter<ScreenShakeEvent>,
+        player: Query<&Transform, With<Player>>,
+        minions: Query<(), With<GoblinMinion>>,
+        mut query: Query<(&mut GoblinKing, &Boss, &Health, &Transform), Without<Dying>>,
+    ) {
+        let player = match player.get_single() {
+            Ok(transform) => transform.translation.truncate(),
+            Err(_) => return,
+        };
+
+        for (mut king, boss, health, transform) in query.iter_mut() {
+            if !boss.is_active() {
+                continue;
+            }
+            king.barrage.tick(Duration::from_secs_f32(TIME_STEP));
+            king.minions.tick(Duration::from_secs_f32(TIME_STEP));
+
+            let position = transform.translation.truncate();
+            let phase = PHASE_THRESHOLDS
+                .iter()
+                .filter(|threshold| health.fraction() <= **threshold)
+                .count();
+            let mut call_minions = king.minions.finished();
+            if phase > king.phase {
+                king.phase = phase;
+                call_minions = true;
+                shake_events.send(ScreenShakeEvent {
+                    intensity: 3.,
+                    duration: 0.3,
+                });
+            }
+
+            if king.barrage.finished() {
+                let origin = position + Vec2::new(0., HALF_SIZE);
+                let aim = (player - origin).normalize_or_zero();
+                for angle in king.barrage() {
+                    let (sin, cos) = angle.sin_cos();
+                    let direction = Vec2::new(aim.x * cos - aim.y * sin, aim.x * sin + aim.y * cos);
+                    commands
+                        .spawn_bundle(ProjectileBundle::new(
+                            &boss_atlases.goblin_king_projectile,
+                            direction * PROJECTILE_SPEED,
+                            PROJECTILE_DAMAGE,
+                            origin.extend(1.),
+                        ))
+                        .insert(Name::new("Goblin King Projectile"));
+                }
+                king.volley += 1;
+                king.barrage = Timer::from_seconds(BARRAGE_INTERVALS[king.phase], false);
+            }
+
+            if call_minions {
+                king.minions = Timer::from_seconds(MINION_INTERVAL, false);
+                let archetype = archetypes.get(Species::Goblin, 0).unwrap();
+                let count = minions.iter().count();
+                for side in [-1., 1.]
+                    .into_iter()
+                    .take(MAX_MINIONS.saturating_sub(count))
+                {
+                    commands
+                        .spawn_bundle(MonsterBundle::from_archetype(
+                            archetype,
+                            monster_atlases.get(archetype),
+                            (position + Vec2::new(side * 32., 0.)).extend(0.),
+                        ))
+                        .insert(GoblinMinion)
+                        .insert(Name::new("Goblin"));
+                }
+            }
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::new()
+            .with_run_criteria(AppState::fixed_step(TIME_STEP))
+            .with_system(GoblinKing::act)
+            .with_system(GoblinKing::attack)
+    }
+}
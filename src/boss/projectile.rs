@@ -0,0 +1,168 @@
+use crate::{
+    dungeon::{Dungeon, TerrainTile},
+    AnimationFinished, Animator, DamageEvent, GameTime, Player, SpriteSheet,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+const SCALE: f32 = 0.15;
+
+/// Distance to the player under which a projectile explodes.
+const HIT_RANGE: f32 = 10.;
+
+const FLY_ANIMATION: &str = "projectile";
+
+const BLAST_ANIMATION: &str = "blast";
+
+/// Seconds after which a projectile blasts on its own.
+const LIFETIME: f32 = 6.;
+
+#[derive(Bundle)]
+pub struct ProjectileBundle {
+    #[bundle]
+    sprite_bundle: SpriteSheetBundle,
+    projectile: Projectile,
+    animator: Animator,
+}
+impl ProjectileBundle {
+    /// Projectile flying with the `projectile` sprites of the sheet, then blasting with its `blast` ones.
+    pub fn new(sheet: &SpriteSheet, velocity: Vec2, damage: f32, at: Vec3) -> Self {
+        ProjectileBundle {
+            sprite_bundle: SpriteSheetBundle {
+                texture_atlas: sheet.atlas.clone(),
+                transform: Transform {
+                    translation: at,
+                    rotation: Quat::from_rotation_z(velocity.y.atan2(velocity.x)),
+                    scale: Vec3::new(SCALE, SCALE, 1.),
+                },
+                ..Default::default()
+            },
+            projectile: Projectile {
+                velocity,
+                damage,
+                turn_rate: 0.,
+                blasting: false,
+                lifetime: Timer::from_seconds(LIFETIME, false),
+            },
+            animator: Animator::new(vec![
+                (FLY_ANIMATION, sheet.animation(FLY_ANIMATION)),
+                (
+                    BLAST_ANIMATION,
+                    sheet.animation(BLAST_ANIMATION).hold_last(),
+                ),
+            ]),
+        }
+    }
+
+    /// Makes the projectile turn toward the player by up to `turn_rate` radians per second.
+    pub fn with_homing(mut self, turn_rate: f32) -> Self {
+        self.projectile.turn_rate = turn_rate;
+        self
+    }
+}
+
+/// Moves until it hits the player or the terrain, then blasts.
+#[derive(Component)]
+pub struct Projectile {
+    velocity: Vec2,
+    damage: f32,
+    turn_rate: f32,
+    blasting: bool,
+    lifetime: Timer,
+}
+impl Projectile {
+    #[allow(clippy::too_many_arguments)]
+    pub fn travel(
+        mut commands: Commands,
+        time: Res<GameTime>,
+        rapier_context: Res<RapierContext>,
+        mut damage_events: EventWriter<DamageEvent>,
+        terrain: Query<(), With<TerrainTile>>,
+        dungeon: Query<&Dungeon>,
+        player: Query<(Entity, &Transform), (With<Player>, Without<Projectile>)>,
+        mut query: Query<(Entity, &mut Projectile, &mut Transform, &mut Animator)>,
+    ) {
+        let is_terrain = |entity| terrain.get(entity).is_ok();
+        let player = player
+            .get_single()
+            .ok()
+            .map(|(entity, transform)| (entity, transform.translation.truncate()));
+        let bounds = dungeon.get_single().ok().map(Dungeon::bounds);
+
+        for (entity, mut projectile, mut transform, mut animator) in query.iter_mut() {
+            if projectile.blasting {
+                continue;
+            }
+            let position = transform.translation.truncate();
+
+            if let Some((_, player_position)) = player {
+                if projectile.turn_rate > 0. {
+                    let wanted = (player_position - position).angle_between(projectile.velocity);
+                    let max_turn = projectile.turn_rate * time.delta_seconds();
+                    let (sin, cos) = (-wanted.clamp(-max_turn, max_turn)).sin_cos();
+                    let velocity = projectile.velocity;
+                    projectile.velocity = Vec2::new(
+                        velocity.x * cos - velocity.y * sin,
+                        velocity.x * sin + velocity.y * cos,
+                    );
+                    transform.rotation =
+                        Quat::from_rotation_z(projectile.velocity.y.atan2(projectile.velocity.x));
+                }
+            }
+
+            let step = projectile.velocity * time.delta_seconds();
+
+            if let Some((min, max)) = bounds {
+                if position.cmplt(min).any() || position.cmpgt(max).any() {
+                    commands.entity(entity).despawn_recursive();
+                    continue;
+                }
+            }
+
+            let hit_player = match player {
+                Some((player, player_position))
+                    if position.distance(player_position) <= HIT_RANGE =>
+                {
+                    damage_events.send(DamageEvent {
+                        target: player,
+                        amount: projectile.damage,
+                    });
+                    true
+                }
+                _ => false,
+            };
+            let hit_terrain = rapier_context
+                .cast_ray(
+                    position,
+                    step,
+                    1.,
+                    true,
+                    InteractionGroups::all(),
+                    Some(&is_terrain),
+                )
+                .is_some();
+
+            let expired = projectile.lifetime.tick(time.delta()).finished();
+
+            if hit_player || hit_terrain || expired {
+                animator.change_animation(BLAST_ANIMATION, false);
+                projectile.blasting = true;
+            } else {
+                transform.translation += step.extend(0.);
+            }
+        }
+    }
+
+    /// Removes projectiles once their blast is over.
+    pub fn despawn(
+        mut commands: Commands,
+        mut finished_events: EventReader<AnimationFinished>,
+        query: Query<(), With<Projectile>>,
+    ) {
+        for AnimationFinished { entity, animation } in finished_events.iter() {
+            if animation == BLAST_ANIMATION && query.get(*entity).is_ok() {
+                commands.entity(*entity).despawn_recursive();
+            }
+        }
+    }
+}
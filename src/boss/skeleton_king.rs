@@ -0,0 +1,168 @@
+use super::{Boss, BossAtlases, BossBundle, BossObstacle, BossObstacleBundle, ENRAGED_TINT, SCALE};
+use crate::{
+    dungeon::Dungeon, AnimationParams, AppState, Dying, Health, Player, ScreenShakeEvent,
+    SpriteEffects,
+};
+use bevy::prelude::*;
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+use bevy_rapier2d::prelude::*;
+use std::time::Duration;
+
+const TIME_STEP: f32 = 1. / 60.;
+
+const OBSTACLE_ANIMATION: &str = "obstacle";
+
+/// Named after the `SkeletonKing_Ostacle_Death` sprites.
+const OBSTACLE_DEATH_ANIMATION: &str = "ostacle_death";
+
+const HEALTH: f32 = 28.;
+
+const DAMAGE: f32 = 2.5;
+
+const WALK_SPEED: f32 = 20.;
+
+/// Health fraction under which the Skeleton King walks faster and raises walls on both sides.
+const ENRAGE_THRESHOLD: f32 = 0.5;
+
+const ENRAGED_WALK_SPEED: f32 = 35.;
+
+const WALL_INTERVAL: f32 = 4.;
+
+const ENRAGED_WALL_INTERVAL: f32 = 2.5;
+
+const WALL_HEALTH: f32 = 4.;
+
+/// Horizontal distance from the player at which bone walls rise.
+const WALL_OFFSET: f32 = 32.;
+
+const MAX_WALLS: usize = 3;
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct SkeletonKing {
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    walls: Timer,
+}
+impl Default for SkeletonKing {
+    fn default() -> Self {
+        SkeletonKing {
+            walls: Timer::from_seconds(WALL_INTERVAL, false),
+        }
+    }
+}
+impl SkeletonKing {
+    pub fn spawn(commands: &mut Commands, atlases: &BossAtlases, at: Vec3) {
+        commands
+            .spawn_bundle(BossBundle::new(&atlases.skeleton_king, HEALTH, DAMAGE, at))
+            .insert(SkeletonKing::default())
+            .insert(Name::new("Skeleton King"));
+    }
+
+    fn act(
+        player: Query<&Transform, With<Player>>,
+        mut query: Query<
+            (
+                &Boss,
+                &Health,
+                &Transform,
+                &mut Velocity,
+                &mut AnimationParams,
+                &mut SpriteEffects,
+            ),
+            (With<SkeletonKing>, Without<Dying>),
+        >,
+    ) {
+        let player = player.get_single().ok().map(|t| t.translation.truncate());
+
+        for (boss, health, transform, mut velocity, mut params, mut effects) in query.iter_mut() {
+            let player = match player {
+                Some(player) if boss.is_active() => player,
+                _ => {
+                    velocity.linvel.x = 0.;
+                    continue;
+                }
+            };
+
+            let speed = if health.fraction() <= ENRAGE_THRESHOLD {
+                effects.tint(ENRAGED_TINT);
+                ENRAGED_WALK_SPEED
+            } else {
+                WALK_SPEED
+            };
+            let direction = (player.x - transform.translation.x).signum();
+            velocity.linvel.x = direction * speed;
+            params.face(direction);
+        }
+    }
+
+    /// Raises bone walls next to the player, which block it until broken.
+    fn raise_walls(
+        mut commands: Commands,
+        atlases: Res<BossAtlases>,
+        mut shake_events: EventWriter<ScreenShakeEvent>,
+        player: Query<&Transform, With<Player>>,
+        walls: Query<(), With<BossObstacle>>,
+        mut query: Query<(&mut SkeletonKing, &Boss, &Health, &Transform), Without<Dying>>,
+    ) {
+        let player = match player.get_single() {
+            Ok(transform) => transform.translation.truncate(),
+            Err(_) => return,
+        };
+        let (min, max) = Dungeon::arena();
+
+        for (mut king, boss, health, transform) in query.iter_mut() {
+            if !boss.is_active() {
+                continue;
+            }
+            if !king
+                .walls
+                .tick(Duration::from_secs_f32(TIME_STEP))
+                .finished()
+            {
+                continue;
+            }
+
+            let enraged = health.fraction() <= ENRAGE_THRESHOLD;
+            king.walls = Timer::from_seconds(
+                if enraged {
+                    ENRAGED_WALL_INTERVAL
+                } else {
+                    WALL_INTERVAL
+                },
+                false,
+            );
+
+            // Behind the player first, to cut its retreat.
+            let behind = (player.x - transform.translation.x).signum();
+            let sides: &[f32] = if enraged { &[1., -1.] } else { &[1.] };
+            let count = walls.iter().count();
+            for side in sides.iter().take(MAX_WALLS.saturating_sub(count)) {
+                let x = player.x + side * behind * WALL_OFFSET;
+                if x < min.x || x > max.x {
+                    continue;
+                }
+                commands
+                    .spawn_bundle(BossObstacleBundle::new(
+                        &atlases.skeleton_king,
+                        OBSTACLE_ANIMATION,
+                        OBSTACLE_DEATH_ANIMATION,
+                        WALL_HEALTH,
+                        Vec3::new(x, min.y + 48. * SCALE, 0.),
+                    ))
+                    .insert(Name::new("Bone Wall"));
+            }
+            shake_events.send(ScreenShakeEvent {
+                intensity: 2.,
+                duration: 0.2,
+            });
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::new()
+            .with_run_criteria(AppState::fixed_step(TIME_STEP))
+            .with_system(SkeletonKing::act)
+            .with_system(SkeletonKing::raise_walls)
+    }
+}
@@ -0,0 +1,170 @@
+use super::{
+    Boss, BossAtlases, BossBundle, BossObstacle, BossObstacleBundle, ENRAGED_TINT, HALF_SIZE, SCALE,
+};
+use crate::{
+    dungeon::TerrainTile, AnimationParams, AppState, Dying, Health, Player, ScreenShakeEvent,
+    SpriteEffects,
+};
+use bevy::prelude::*;
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+use bevy_rapier2d::prelude::*;
+use std::time::Duration;
+
+const TIME_STEP: f32 = 1. / 60.;
+
+const OBSTACLE_ANIMATION: &str = "obstacle";
+
+const OBSTACLE_DEATH_ANIMATION: &str = "obstacle_death";
+
+const HEALTH: f32 = 20.;
+
+const DAMAGE: f32 = 2.;
+
+const WALK_SPEED: f32 = 20.;
+
+const JUMP_SPEED: f32 = 60.;
+
+const JUMP_IMPULSE: f32 = 120.;
+
+const JUMP_INTERVAL: f32 = 3.;
+
+/// Health fraction under which the Slime King jumps faster and spawns obstacles on landing.
+const ENRAGE_THRESHOLD: f32 = 0.5;
+
+const ENRAGED_JUMP_INTERVAL: f32 = 1.5;
+
+const OBSTACLE_HEALTH: f32 = 3.;
+
+const OBSTACLE_OFFSET: f32 = 40.;
+
+const MAX_OBSTACLES: usize = 4;
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct SlimeKing {
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    jump: Timer,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    airborne: bool,
+}
+impl Default for SlimeKing {
+    fn default() -> Self {
+        SlimeKing {
+            jump: Timer::from_seconds(JUMP_INTERVAL, false),
+            airborne: false,
+        }
+    }
+}
+impl SlimeKing {
+    pub fn spawn(commands: &mut Commands, atlases: &BossAtlases, at: Vec3) {
+        commands
+            .spawn_bundle(BossBundle::new(&atlases.slime_king, HEALTH, DAMAGE, at))
+            .insert(SlimeKing::default())
+            .insert(Name::new("Slime King"));
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn act(
+        mut commands: Commands,
+        atlases: Res<BossAtlases>,
+        rapier_context: Res<RapierContext>,
+        mut shake_events: EventWriter<ScreenShakeEvent>,
+        terrain: Query<(), With<TerrainTile>>,
+        player: Query<&Transform, With<Player>>,
+        obstacles: Query<(), With<BossObstacle>>,
+        mut query: Query<
+            (
+                &mut SlimeKing,
+                &Boss,
+                &Health,
+                &Transform,
+                &mut Velocity,
+                &mut AnimationParams,
+                &mut SpriteEffects,
+            ),
+            Without<Dying>,
+        >,
+    ) {
+        let is_terrain = |entity| terrain.get(entity).is_ok();
+        let player = player.get_single().ok().map(|t| t.translation.truncate());
+
+        for (mut king, boss, health, transform, mut velocity, mut params, mut effects) in
+            query.iter_mut()
+        {
+            let player = match player {
+                Some(player) if boss.is_active() => player,
+                _ => {
+                    velocity.linvel.x = 0.;
+                    continue;
+                }
+            };
+            king.jump.tick(Duration::from_secs_f32(TIME_STEP));
+
+            let position = transform.translation.truncate();
+            let grounded = rapier_context
+                .cast_ray(
+                    position,
+                    -Vec2::Y,
+                    HALF_SIZE + 1.,
+                    true,
+                    InteractionGroups::all(),
+                    Some(&is_terrain),
+                )
+                .is_some();
+            if !grounded {
+                king.airborne = true;
+                continue;
+            }
+
+            let enraged = health.fraction() <= ENRAGE_THRESHOLD;
+            if enraged {
+                effects.tint(ENRAGED_TINT);
+            }
+            if king.airborne {
+                king.airborne = false;
+                shake_events.send(ScreenShakeEvent {
+                    intensity: 3.,
+                    duration: 0.2,
+                });
+                if enraged && obstacles.iter().count() + 2 <= MAX_OBSTACLES {
+                    for side in [-1., 1.] {
+                        commands
+                            .spawn_bundle(BossObstacleBundle::new(
+                                &atlases.slime_king,
+                                OBSTACLE_ANIMATION,
+                                OBSTACLE_DEATH_ANIMATION,
+                                OBSTACLE_HEALTH,
+                                Vec3::new(
+                                    position.x + side * OBSTACLE_OFFSET,
+                                    position.y - HALF_SIZE + 48. * SCALE,
+                                    0.,
+                                ),
+                            ))
+                            .insert(Name::new("Slime Obstacle"));
+                    }
+                }
+            }
+
+            let direction = (player.x - position.x).signum();
+            velocity.linvel.x = direction * WALK_SPEED;
+            if king.jump.finished() {
+                velocity.linvel = Vec2::new(direction * JUMP_SPEED, JUMP_IMPULSE);
+                let interval = if enraged {
+                    ENRAGED_JUMP_INTERVAL
+                } else {
+                    JUMP_INTERVAL
+                };
+                king.jump = Timer::from_seconds(interval, false);
+            }
+
+            params.face(direction);
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::new()
+            .with_run_criteria(AppState::fixed_step(TIME_STEP))
+            .with_system(SlimeKing::act)
+    }
+}
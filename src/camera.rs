@@ -0,0 +1,144 @@
+use crate::{dungeon::Dungeon, AppState, GameTime, Player};
+use bevy::{prelude::*, render::camera::Camera2d};
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+use rand::Rng;
+
+pub struct ScreenShakeEvent {
+    pub intensity: f32,
+    pub duration: f32,
+}
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct CameraController {
+    /// How fast the camera catches up with its target, higher is snappier.
+    damping: f32,
+    /// Distance the camera leads the player in its movement direction.
+    look_ahead: f32,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    position: Option<Vec2>,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    last_target: Vec2,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    direction: f32,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    shake_intensity: f32,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    shake: Timer,
+}
+impl Default for CameraController {
+    fn default() -> Self {
+        CameraController {
+            damping: 5.,
+            look_ahead: 32.,
+            position: None,
+            last_target: Vec2::ZERO,
+            direction: 0.,
+            shake_intensity: 0.,
+            shake: Timer::from_seconds(0., false),
+        }
+    }
+}
+impl CameraController {
+    fn follow_player(
+        time: Res<GameTime>,
+        mut shake_events: EventReader<ScreenShakeEvent>,
+        windows: Res<Windows>,
+        player: Query<&Transform, (With<Player>, Without<CameraController>)>,
+        dungeon: Query<&Dungeon>,
+        mut camera: Query<
+            (
+                &mut CameraController,
+                &mut Transform,
+                &OrthographicProjection,
+            ),
+            With<Camera2d>,
+        >,
+    ) {
+        if player.is_empty() || camera.is_empty() {
+            return;
+        }
+
+        let target = player.single().translation.truncate();
+        let (mut controller, mut transform, projection) = camera.single_mut();
+
+        for ScreenShakeEvent {
+            intensity,
+            duration,
+        } in shake_events.iter()
+        {
+            if controller.shake.finished() || *intensity > controller.shake_intensity {
+                controller.shake_intensity = *intensity;
+                controller.shake = Timer::from_seconds(*duration, false);
+            }
+        }
+
+        if controller.position.is_none() {
+            controller.last_target = target;
+        }
+        let movement = target.x - controller.last_target.x;
+        if movement.abs() > f32::EPSILON {
+            controller.direction = movement.signum();
+        }
+        controller.last_target = target;
+
+        let goal = target + Vec2::new(controller.direction * controller.look_ahead, 0.);
+        let position = match controller.position {
+            Some(position) => {
+                let t = 1. - (-controller.damping * time.delta_seconds()).exp();
+                position.lerp(goal, t)
+            }
+            None => goal,
+        };
+
+        let position = match (dungeon.get_single(), windows.get_primary()) {
+            (Ok(dungeon), Some(window)) => {
+                let half_view = Vec2::new(window.width(), window.height()) * projection.scale / 2.;
+                let (min, max) = dungeon.bounds();
+                Vec2::new(
+                    clamp_axis(position.x, min.x + half_view.x, max.x - half_view.x),
+                    clamp_axis(position.y, min.y + half_view.y, max.y - half_view.y),
+                )
+            }
+            _ => position,
+        };
+        controller.position = Some(position);
+
+        controller.shake.tick(time.delta());
+        let shake = if controller.shake.finished() {
+            Vec2::ZERO
+        } else {
+            let strength = controller.shake_intensity * controller.shake.percent_left();
+            let mut rng = rand::thread_rng();
+            Vec2::new(rng.gen_range(-1. ..=1.), rng.gen_range(-1. ..=1.)) * strength
+        };
+
+        transform.translation.x = position.x + shake.x;
+        transform.translation.y = position.y + shake.y;
+    }
+
+    fn reset(mut camera: Query<&mut CameraController>) {
+        for mut controller in camera.iter_mut() {
+            controller.position = None;
+            controller.direction = 0.;
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::on_update(AppState::RunningGame).with_system(CameraController::follow_player)
+    }
+
+    pub fn reset_system_set() -> SystemSet {
+        SystemSet::on_exit(AppState::RunningGame).with_system(CameraController::reset)
+    }
+}
+
+/// Clamps `value` between `min` and `max`, centering it when the range is empty.
+fn clamp_axis(value: f32, min: f32, max: f32) -> f32 {
+    if min > max {
+        (min + max) / 2.
+    } else {
+        value.clamp(min, max)
+    }
+}
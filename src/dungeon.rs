@@ -1,9 +1,13 @@
+mod navigation;
 mod terrain;
 
 use crate::AppState;
 use bevy::prelude::*;
+pub use navigation::*;
 use rand::distributions::{Distribution, Standard};
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
 use std::ops::{Index, IndexMut, SubAssign};
 pub use terrain::*;
 
@@ -11,8 +15,33 @@ const VERTICAL_SIZE: usize = 16;
 
 const TILE_SET_SIZE: usize = 4;
 
+const DUNGEON_SIZE: usize = 4;
+
 const SPRITE_SIZE: f32 = 16.;
 
+/// Width, in tiles, of the boss arena built to the right of the dungeon.
+const ARENA_WIDTH: usize = 24;
+
+const ARENA_HEIGHT: usize = 14;
+
+/// Height, in tiles, of the gap left in the arena's right wall for its door.
+const ARENA_DOOR_HEIGHT: usize = 3;
+
+/// Cells reserved for flying monsters on each floor.
+const FLYING_MONSTERS: usize = 2;
+
+const CHESTS: usize = 2;
+
+/// Dungeon generation and the navigation grid of ground monsters.
+pub struct DungeonPlugin;
+impl Plugin for DungeonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavGrid>()
+            .add_event::<PathRequest>()
+            .add_system_set(Dungeon::system_set());
+    }
+}
+
 struct TileSet {
     tiles: [[Possibility; TILE_SET_SIZE]; TILE_SET_SIZE],
 }
@@ -181,23 +210,120 @@ impl IndexMut<Pos> for Dungeon {
     }
 }
 
+pub struct DungeonAtlases {
+    pub terrain: Handle<TextureAtlas>,
+}
+
 #[derive(Component)]
 pub struct Dungeon {
     terrain_atlas: Handle<TextureAtlas>,
     has_generate: bool,
     content: Vec<[Tile; VERTICAL_SIZE]>,
+    rng: StdRng,
 }
 impl Dungeon {
-    pub fn new(terrain_atlas: Handle<TextureAtlas>) -> Self {
-        Dungeon {
+    /// Lays out the tiles of a floor from `seed`, spawned once the dungeon is.
+    pub fn new(terrain_atlas: Handle<TextureAtlas>, seed: u64) -> Self {
+        let mut dungeon = Dungeon {
             terrain_atlas,
             has_generate: false,
             content: vec![],
+            rng: StdRng::seed_from_u64(seed),
+        };
+        dungeon.fill();
+        dungeon.place(Possibility::FlyingMonster, FLYING_MONSTERS);
+        dungeon.place(Possibility::Chest, CHESTS);
+        dungeon
+    }
+
+    /// Lower left and upper right corners of the generated tiles, in world space.
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let size = (DUNGEON_SIZE * TILE_SET_SIZE) as f32 * SPRITE_SIZE;
+        let width = size + ARENA_WIDTH as f32 * SPRITE_SIZE;
+        let height = size.max(ARENA_HEIGHT as f32 * SPRITE_SIZE);
+        (
+            Vec2::splat(-SPRITE_SIZE / 2.),
+            Vec2::new(width, height) - SPRITE_SIZE / 2.,
+        )
+    }
+
+    /// Number of tile columns.
+    pub fn width(&self) -> usize {
+        self.content.len()
+    }
+
+    pub fn height(&self) -> usize {
+        VERTICAL_SIZE
+    }
+
+    pub fn is_terrain(&self, x: usize, y: usize) -> bool {
+        self.content
+            .get(x)
+            .and_then(|column| column.get(y))
+            .is_some_and(|tile| *tile == Possibility::Terrain)
+    }
+
+    /// Tile containing `position`, if it is inside the dungeon's grid.
+    pub fn cell(&self, position: Vec2) -> Option<(usize, usize)> {
+        let cell = ((position + SPRITE_SIZE / 2.) / SPRITE_SIZE).floor();
+        if cell.x < 0. || cell.y < 0. {
+            return None;
         }
+        let (x, y) = (cell.x as usize, cell.y as usize);
+        (x < self.width() && y < self.height()).then_some((x, y))
+    }
+
+    /// Centers of the cells where flying monsters spawn.
+    pub fn flying_monster_spawns(&self) -> Vec<Vec2> {
+        self.cells(Possibility::FlyingMonster)
+    }
+
+    /// Centers of the cells where chests spawn.
+    pub fn chest_spawns(&self) -> Vec<Vec2> {
+        self.cells(Possibility::Chest)
+    }
+
+    fn cells(&self, possibility: Possibility) -> Vec<Vec2> {
+        let mut cells = vec![];
+        for (x, column) in self.content.iter().enumerate() {
+            for (y, tile) in column.iter().enumerate() {
+                if *tile == possibility {
+                    cells.push(Dungeon::cell_center(x, y));
+                }
+            }
+        }
+        cells
+    }
+
+    pub fn cell_center(x: usize, y: usize) -> Vec2 {
+        Vec2::new(x as f32, y as f32) * SPRITE_SIZE
+    }
+
+    /// Lower left and upper right corners of the boss arena's free space, in world space.
+    pub fn arena() -> (Vec2, Vec2) {
+        let left = (DUNGEON_SIZE * TILE_SET_SIZE) as f32 * SPRITE_SIZE;
+        let right = left + (ARENA_WIDTH - 1) as f32 * SPRITE_SIZE;
+        let top = (ARENA_HEIGHT - 1) as f32 * SPRITE_SIZE;
+        (
+            Vec2::new(left, SPRITE_SIZE) - SPRITE_SIZE / 2.,
+            Vec2::new(right, top) - SPRITE_SIZE / 2.,
+        )
+    }
+
+    /// Center of the door closing the boss arena's right wall.
+    pub fn arena_door() -> Vec2 {
+        let (_, max) = Dungeon::arena();
+        Vec2::new(
+            max.x + SPRITE_SIZE / 2.,
+            (ARENA_DOOR_HEIGHT as f32 + 1.) * SPRITE_SIZE / 2.,
+        )
     }
 
     pub fn system_set() -> SystemSet {
-        SystemSet::on_update(AppState::RunningGame).with_system(Dungeon::generate)
+        SystemSet::on_update(AppState::RunningGame)
+            .with_system(Dungeon::generate)
+            .with_system(NavGrid::rebuild.after(Dungeon::generate))
+            .with_system(NavGrid::find_paths.after(NavGrid::rebuild))
     }
 
     fn relative_pos(&mut self, mut pos: Pos, right: i32, above: i32) -> Option<Pos> {
@@ -334,6 +460,59 @@ impl Dungeon {
         }
     }
 
+    /// Fills the tile sets and the boss arena.
+    fn fill(&mut self) {
+        let size = DUNGEON_SIZE * TILE_SET_SIZE;
+        self.content = vec![[Tile::from(Possibility::Air); VERTICAL_SIZE]; size + ARENA_WIDTH];
+        for origin_x in 0..DUNGEON_SIZE {
+            for origin_y in 0..DUNGEON_SIZE {
+                let tile_set = &TILE_SETS[self.rng.gen_range(0..TILE_SETS.len())];
+                for x in 0..TILE_SET_SIZE {
+                    for y in 0..TILE_SET_SIZE {
+                        let pos = Pos(origin_x * TILE_SET_SIZE + x, origin_y * TILE_SET_SIZE + y);
+                        self[pos] = Tile::from(tile_set.tiles[x][y]);
+                    }
+                }
+            }
+        }
+
+        // Boss arena
+        for x in 0..ARENA_WIDTH {
+            for y in 0..ARENA_HEIGHT {
+                let is_floor = y == 0;
+                let is_ceiling = y == ARENA_HEIGHT - 1;
+                let is_wall = x == ARENA_WIDTH - 1 && y > ARENA_DOOR_HEIGHT;
+                if is_floor || is_ceiling || is_wall {
+                    self[Pos(size + x, y)] = Tile::from(Possibility::Terrain);
+                }
+            }
+        }
+    }
+
+    /// Reserves `count` cells of the tile sets past the player's start for `possibility`.
+    fn place(&mut self, possibility: Possibility, count: usize) {
+        let size = DUNGEON_SIZE * TILE_SET_SIZE;
+        let air = |x: usize, y: usize| self.content[x][y] == Possibility::Air;
+        let cells: Vec<Pos> = (TILE_SET_SIZE..size)
+            .flat_map(|x| (1..VERTICAL_SIZE - 1).map(move |y| Pos(x, y)))
+            .filter(|&Pos(x, y)| match possibility {
+                // Flies over open air, with room to hover.
+                Possibility::FlyingMonster => air(x, y - 1) && air(x, y) && air(x, y + 1),
+                // Rests on the ground.
+                Possibility::Chest => air(x, y) && self.is_terrain(x, y - 1),
+                _ => false,
+            })
+            .collect();
+
+        let chosen: Vec<Pos> = cells
+            .choose_multiple(&mut self.rng, count)
+            .copied()
+            .collect();
+        for pos in chosen {
+            self[pos] = Tile::from(possibility);
+        }
+    }
+
     fn collapse(&mut self, extends: usize) {
         let start_new_content = self.content.len();
         let content_len = if start_new_content == 0 {
@@ -386,27 +565,16 @@ impl Dungeon {
         }
 
         dungeon.has_generate = true;
+
         commands.entity(entity).with_children(|d| {
-            for origin_x in 0..4 {
-                for origin_y in 0..4 {
-                    for x in 0..TILE_SET_SIZE {
-                        for y in 0..TILE_SET_SIZE {
-                            match TILE_SETS[0].tiles[x][y] {
-                                Possibility::Terrain => {
-                                    let x = origin_x as f32 * SPRITE_SIZE * TILE_SET_SIZE as f32
-                                        + x as f32 * SPRITE_SIZE;
-                                    let y = origin_y as f32 * SPRITE_SIZE * TILE_SET_SIZE as f32
-                                        + y as f32 * SPRITE_SIZE;
-
-                                    d.spawn_bundle(TerrainTileBundle::new(
-                                        dungeon.terrain_atlas.clone(),
-                                        27,
-                                        Vec3::new(x, y, 0.),
-                                    ));
-                                }
-                                _ => {}
-                            }
-                        }
+            for x in 0..dungeon.width() {
+                for y in 0..VERTICAL_SIZE {
+                    if dungeon.is_terrain(x, y) {
+                        d.spawn_bundle(TerrainTileBundle::new(
+                            dungeon.terrain_atlas.clone(),
+                            27,
+                            Dungeon::cell_center(x, y).extend(0.),
+                        ));
                     }
                 }
             }
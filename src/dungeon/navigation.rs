@@ -0,0 +1,332 @@
+use super::{Dungeon, SPRITE_SIZE};
+use bevy::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Farthest column reachable by a jump.
+const MAX_JUMP_DISTANCE: usize = 3;
+
+/// Highest row reachable by a jump.
+const MAX_JUMP_HEIGHT: usize = 2;
+
+/// Horizontal distance under which a waypoint counts as reached.
+const WAYPOINT_RANGE: f32 = 4.;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Move {
+    Walk,
+    /// Steps off a ledge and falls onto a lower surface.
+    Drop,
+    /// Jumps up a step or across a gap.
+    Jump,
+}
+
+/// Tile to reach, and how to get there from the previous one.
+#[derive(Clone, Copy, Debug)]
+pub struct PathStep {
+    pub position: Vec2,
+    pub movement: Move,
+}
+
+/// Asks for a path from the entity's position to `goal`, stored in its `Path`.
+pub struct PathRequest {
+    pub entity: Entity,
+    pub goal: Vec2,
+}
+
+/// Blocks the cells under it for ground monsters, such as a wall raised by a boss.
+#[derive(Component)]
+pub struct NavObstacle {
+    pub half_size: Vec2,
+}
+
+/// Route followed by a ground monster, recomputed whenever the navigation grid changes.
+#[derive(Component)]
+pub struct Path {
+    goal: Vec2,
+    steps: Vec<PathStep>,
+}
+impl Path {
+    /// Next step toward the goal from `position`, dropping the waypoints already reached.
+    pub fn next(&mut self, position: Vec2) -> Option<PathStep> {
+        while let Some(step) = self.steps.first() {
+            let reached = (step.position.x - position.x).abs() <= WAYPOINT_RANGE
+                && (step.position.y - position.y).abs() <= SPRITE_SIZE;
+            if !reached {
+                break;
+            }
+            self.steps.remove(0);
+        }
+        self.steps.first().copied()
+    }
+}
+
+/// Surfaces a ground monster can stand on, and the moves between them.
+#[derive(Default)]
+pub struct NavGrid {
+    width: usize,
+    height: usize,
+    terrain: Vec<bool>,
+}
+impl NavGrid {
+    /// Cell containing `position`, which may lie outside of the grid.
+    fn cell(position: Vec2) -> (isize, isize) {
+        let cell = ((position + SPRITE_SIZE / 2.) / SPRITE_SIZE).floor();
+        (cell.x as isize, cell.y as isize)
+    }
+
+    /// Marks the cells overlapping the rectangle between `min` and `max` as terrain.
+    fn block(&mut self, min: Vec2, max: Vec2) {
+        let (min_x, min_y) = NavGrid::cell(min);
+        let (max_x, max_y) = NavGrid::cell(max);
+        for x in min_x.max(0)..=max_x.min(self.width as isize - 1) {
+            for y in min_y.max(0)..=max_y.min(self.height as isize - 1) {
+                self.terrain[x as usize * self.height + y as usize] = true;
+            }
+        }
+    }
+
+    fn is_terrain(&self, x: isize, y: isize) -> bool {
+        if x < 0 || x >= self.width as isize {
+            return true;
+        }
+        if y < 0 || y >= self.height as isize {
+            return false;
+        }
+        self.terrain[x as usize * self.height + y as usize]
+    }
+
+    fn is_air(&self, x: isize, y: isize) -> bool {
+        !self.is_terrain(x, y)
+    }
+
+    /// Whether a monster can stand in the cell, that is an air cell above terrain.
+    fn is_standing(&self, x: isize, y: isize) -> bool {
+        y > 0 && y < self.height as isize && self.is_air(x, y) && self.is_terrain(x, y - 1)
+    }
+
+    /// First standing cell at or below `(x, y)`, falling through air.
+    fn land(&self, x: isize, mut y: isize) -> Option<(isize, isize)> {
+        while y > 0 && self.is_air(x, y) {
+            if self.is_standing(x, y) {
+                return Some((x, y));
+            }
+            y -= 1;
+        }
+        None
+    }
+
+    fn neighbours(&self, (x, y): (isize, isize)) -> Vec<((isize, isize), Move, u32)> {
+        let mut neighbours = vec![];
+
+        for direction in [-1, 1] {
+            let next = x + direction;
+            if self.is_standing(next, y) {
+                neighbours.push(((next, y), Move::Walk, 2));
+            } else if self.is_air(next, y) {
+                if let Some((_, landing)) = self.land(next, y) {
+                    neighbours.push(((next, landing), Move::Drop, 2 + (y - landing) as u32));
+                }
+            }
+
+            for dx in 1..=MAX_JUMP_DISTANCE as isize {
+                for dy in -(MAX_JUMP_HEIGHT as isize)..=MAX_JUMP_HEIGHT as isize {
+                    if (dx == 1 && dy <= 0) || !self.is_standing(x + direction * dx, y + dy) {
+                        continue;
+                    }
+                    let apex = y + dy.max(0) + 1;
+                    let clear = (0..=dx).all(|i| {
+                        let column = x + direction * i;
+                        let bottom = if i == 0 || i == dx { y.max(y + dy) } else { y };
+                        (bottom..=apex).all(|row| self.is_air(column, row))
+                    });
+                    if clear {
+                        let target = (x + direction * dx, y + dy);
+                        // Dearer than walking as far, so that monsters only jump when they must.
+                        neighbours.push((target, Move::Jump, 3 + (2 * dx + dy.abs()) as u32));
+                    }
+                }
+            }
+        }
+
+        neighbours
+    }
+
+    /// Cheapest path between the cells of `from` and `to`, with A*.
+    fn find(&self, from: Vec2, to: Vec2) -> Option<Vec<PathStep>> {
+        let cell = |position| {
+            let (x, y) = NavGrid::cell(position);
+            let inside =
+                (0..self.width as isize).contains(&x) && (0..self.height as isize).contains(&y);
+            inside.then(|| self.land(x, y)).flatten()
+        };
+        let start = cell(from)?;
+        let goal = cell(to)?;
+        let heuristic = |(x, y): (isize, isize)| ((x - goal.0).abs() + (y - goal.1).abs()) as u32;
+
+        let mut open = BinaryHeap::from([Reverse((heuristic(start), 0, start))]);
+        let mut came_from = HashMap::new();
+        let mut costs = HashMap::from([(start, 0)]);
+
+        while let Some(Reverse((_, cost, current))) = open.pop() {
+            if current == goal {
+                let mut steps = vec![];
+                let mut cell = current;
+                while let Some(&(previous, movement)) = came_from.get(&cell) {
+                    steps.push(PathStep {
+                        position: Dungeon::cell_center(cell.0 as usize, cell.1 as usize),
+                        movement,
+                    });
+                    cell = previous;
+                }
+                steps.reverse();
+                return Some(steps);
+            }
+            if cost > costs[&current] {
+                continue;
+            }
+
+            for (next, movement, step_cost) in self.neighbours(current) {
+                let next_cost = cost + step_cost;
+                if costs.get(&next).is_none_or(|&known| next_cost < known) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, (current, movement));
+                    open.push(Reverse((next_cost + heuristic(next), next_cost, next)));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Mirrors the dungeon's tiles, blocked by the obstacles on them, whenever either changes.
+    pub fn rebuild(
+        mut grid: ResMut<NavGrid>,
+        dungeon: Query<(&Dungeon, ChangeTrackers<Dungeon>)>,
+        obstacles: Query<(&Transform, &NavObstacle)>,
+        moved_obstacles: Query<(), (With<NavObstacle>, Changed<Transform>)>,
+        removed_obstacles: RemovedComponents<NavObstacle>,
+    ) {
+        let (dungeon, tracker) = match dungeon.get_single() {
+            Ok(dungeon) => dungeon,
+            Err(_) => return,
+        };
+        if !tracker.is_changed()
+            && moved_obstacles.is_empty()
+            && removed_obstacles.iter().next().is_none()
+        {
+            return;
+        }
+
+        *grid = NavGrid {
+            width: dungeon.width(),
+            height: dungeon.height(),
+            terrain: (0..dungeon.width())
+                .flat_map(|x| (0..dungeon.height()).map(move |y| (x, y)))
+                .map(|(x, y)| dungeon.is_terrain(x, y))
+                .collect(),
+        };
+        for (transform, obstacle) in obstacles.iter() {
+            let center = transform.translation.truncate();
+            grid.block(center - obstacle.half_size, center + obstacle.half_size);
+        }
+    }
+
+    /// Answers path requests, and recomputes every path once the grid changed.
+    pub fn find_paths(
+        mut commands: Commands,
+        grid: Res<NavGrid>,
+        mut requests: EventReader<PathRequest>,
+        transforms: Query<&Transform>,
+        mut paths: Query<(&Transform, &mut Path)>,
+    ) {
+        if grid.is_changed() {
+            for (transform, mut path) in paths.iter_mut() {
+                let goal = path.goal;
+                path.steps = grid
+                    .find(transform.translation.truncate(), goal)
+                    .unwrap_or_default();
+            }
+        }
+
+        for PathRequest { entity, goal } in requests.iter() {
+            if let Ok(transform) = transforms.get(*entity) {
+                let steps = grid
+                    .find(transform.translation.truncate(), *goal)
+                    .unwrap_or_default();
+                commands.entity(*entity).insert(Path { goal: *goal, steps });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grid drawn with its top row first, `#` for terrain and `.` for air.
+    fn grid(rows: &[&str]) -> NavGrid {
+        let height = rows.len();
+        let width = rows[0].len();
+        NavGrid {
+            width,
+            height,
+            terrain: (0..width)
+                .flat_map(|x| (0..height).map(move |y| (x, y)))
+                .map(|(x, y)| rows[height - 1 - y].as_bytes()[x] == b'#')
+                .collect(),
+        }
+    }
+
+    fn find(grid: &NavGrid, from: (usize, usize), to: (usize, usize)) -> Option<Vec<PathStep>> {
+        grid.find(
+            Dungeon::cell_center(from.0, from.1),
+            Dungeon::cell_center(to.0, to.1),
+        )
+    }
+
+    #[test]
+    fn walks_along_flat_ground() {
+        let grid = grid(&["......", "......", "######"]);
+        let steps = find(&grid, (0, 1), (5, 1)).unwrap();
+
+        assert_eq!(steps.len(), 5);
+        assert!(steps.iter().all(|step| step.movement == Move::Walk));
+        assert_eq!(steps.last().unwrap().position, Dungeon::cell_center(5, 1));
+    }
+
+    #[test]
+    fn drops_off_ledges() {
+        let grid = grid(&["......", "......", "###...", "......", "######"]);
+        let steps = find(&grid, (0, 3), (5, 1)).unwrap();
+
+        assert!(steps.iter().any(|step| step.movement == Move::Drop));
+        assert!(steps.iter().all(|step| step.movement != Move::Jump));
+        assert_eq!(steps.last().unwrap().position, Dungeon::cell_center(5, 1));
+    }
+
+    #[test]
+    fn jumps_onto_ledges() {
+        let grid = grid(&["......", "......", "###...", "......", "######"]);
+        let steps = find(&grid, (5, 1), (0, 3)).unwrap();
+
+        assert!(steps.iter().any(|step| step.movement == Move::Jump));
+        assert_eq!(steps.last().unwrap().position, Dungeon::cell_center(0, 3));
+    }
+
+    #[test]
+    fn fails_on_unreachable_targets() {
+        let grid = grid(&["...#..", "...#..", "...#..", "...#..", "######"]);
+
+        assert!(find(&grid, (0, 1), (5, 1)).is_none());
+    }
+
+    #[test]
+    fn avoids_blocked_cells() {
+        let mut grid = grid(&["######", "......", "######"]);
+        assert!(find(&grid, (0, 1), (5, 1)).is_some());
+
+        grid.block(Dungeon::cell_center(3, 1), Dungeon::cell_center(3, 1));
+        assert!(find(&grid, (0, 1), (5, 1)).is_none());
+    }
+}
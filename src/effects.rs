@@ -0,0 +1,198 @@
+use crate::{AnimationParams, AppState, GameTime};
+use bevy::{math::const_vec2, prelude::*};
+use std::ops::{Add, Mul, Sub};
+use std::time::Duration;
+
+const FLASH_DURATION: f32 = 0.15;
+
+/// Sprite colors are multiplied with the texture, so channels above one brighten it.
+pub const FLASH_WHITE: Color = Color::rgb(4., 4., 4.);
+
+pub const FLASH_RED: Color = Color::rgb(4., 0.6, 0.6);
+
+const BLINK_PERIOD: f32 = 0.1;
+
+const BLINK_ALPHA: f32 = 0.3;
+
+const TINT_DURATION: f32 = 0.5;
+
+const SQUASH_DURATION: f32 = 0.25;
+
+/// Scale of a character touching the ground after a fall.
+const LANDING_SQUASH: Vec2 = const_vec2!([1.25, 0.75]);
+
+/// Scale of a character leaving the ground.
+const JUMP_STRETCH: Vec2 = const_vec2!([0.8, 1.2]);
+
+/// How a tween progresses through its duration.
+#[derive(Clone, Copy, Debug)]
+pub enum Ease {
+    Linear,
+    /// Fast at first, slowing down toward the end.
+    Out,
+    /// Slow at both ends.
+    InOut,
+}
+impl Ease {
+    fn apply(&self, t: f32) -> f32 {
+        match self {
+            Ease::Linear => t,
+            Ease::Out => 1. - (1. - t) * (1. - t),
+            Ease::InOut => t * t * (3. - 2. * t),
+        }
+    }
+}
+
+/// Value going from `from` to `to` over a duration.
+#[derive(Clone, Debug)]
+pub struct Tween<T> {
+    from: T,
+    to: T,
+    ease: Ease,
+    timer: Timer,
+}
+impl<T> Tween<T>
+where
+    T: Copy + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    pub fn new(from: T, to: T, duration: f32, ease: Ease) -> Self {
+        Tween {
+            from,
+            to,
+            ease,
+            timer: Timer::from_seconds(duration, false),
+        }
+    }
+
+    /// Tween which is already over, staying on `value`.
+    fn fixed(value: T) -> Self {
+        Tween::new(value, value, 0., Ease::Linear)
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        self.timer.tick(delta);
+    }
+
+    pub fn value(&self) -> T {
+        let t = if self.timer.duration().is_zero() {
+            1.
+        } else {
+            self.timer.percent()
+        };
+        self.from + (self.to - self.from) * self.ease.apply(t)
+    }
+
+    fn target(&self) -> T {
+        self.to
+    }
+}
+
+/// Color and scale effects layered over the sprite of the holder, each driven by a tween.
+///
+/// The color the sprite had when the effects first applied is kept as its base, such as the
+/// tint of a monster archetype. Squashing resizes the sprite rather than the `Transform`, which
+/// would scale the collider along with it.
+#[derive(Component)]
+pub struct SpriteEffects {
+    flash: Tween<Vec4>,
+    tint: Tween<Vec4>,
+    fade: Tween<f32>,
+    scale: Tween<Vec2>,
+    blink: Timer,
+    base_color: Option<Color>,
+}
+impl Default for SpriteEffects {
+    fn default() -> Self {
+        SpriteEffects {
+            flash: Tween::fixed(Vec4::ONE),
+            tint: Tween::fixed(Vec4::ONE),
+            fade: Tween::fixed(1.),
+            scale: Tween::fixed(Vec2::ONE),
+            blink: Timer::from_seconds(0., false),
+            base_color: None,
+        }
+    }
+}
+impl SpriteEffects {
+    /// Briefly lights the sprite up with `color`.
+    pub fn flash(&mut self, color: Color) {
+        self.flash = Tween::new(Vec4::from(color), Vec4::ONE, FLASH_DURATION, Ease::Out);
+    }
+
+    /// Tints the sprite with `color`, such as for a status effect, until tinted `Color::WHITE`.
+    pub fn tint(&mut self, color: Color) {
+        let color = Vec4::from(color);
+        if self.tint.target() != color {
+            self.tint = Tween::new(self.tint.value(), color, TINT_DURATION, Ease::InOut);
+        }
+    }
+
+    /// Makes the sprite transparent over `duration` seconds.
+    pub fn fade_out(&mut self, duration: f32) {
+        self.blink = Timer::from_seconds(0., false);
+        self.fade = Tween::new(self.fade.value(), 0., duration, Ease::Linear);
+    }
+
+    /// Makes the sprite flicker for `duration` seconds.
+    pub fn blink(&mut self, duration: f32) {
+        self.blink = Timer::from_seconds(duration, false);
+    }
+
+    /// Scales the sprite by `scale`, springing back to its size.
+    pub fn squash(&mut self, scale: Vec2) {
+        self.scale = Tween::new(scale, Vec2::ONE, SQUASH_DURATION, Ease::Out);
+    }
+
+    fn apply(
+        time: Res<GameTime>,
+        texture_atlases: Res<Assets<TextureAtlas>>,
+        mut query: Query<(
+            &mut SpriteEffects,
+            &mut TextureAtlasSprite,
+            &Handle<TextureAtlas>,
+        )>,
+    ) {
+        for (mut effects, mut sprite, atlas) in query.iter_mut() {
+            let effects = &mut *effects;
+            let base_color = *effects.base_color.get_or_insert(sprite.color);
+
+            effects.flash.tick(time.delta());
+            effects.tint.tick(time.delta());
+            effects.fade.tick(time.delta());
+            effects.scale.tick(time.delta());
+            effects.blink.tick(time.delta());
+
+            let blinking = !effects.blink.finished()
+                && effects.blink.elapsed_secs() % (2. * BLINK_PERIOD) < BLINK_PERIOD;
+            let alpha = effects.fade.value() * if blinking { BLINK_ALPHA } else { 1. };
+
+            let color = Vec4::from(base_color) * effects.tint.value() * effects.flash.value();
+            sprite.color = *Color::from(color).set_a(base_color.a() * alpha);
+
+            let scale = effects.scale.value();
+            sprite.custom_size = if scale == Vec2::ONE {
+                None
+            } else {
+                texture_atlases
+                    .get(atlas)
+                    .and_then(|atlas| atlas.textures.get(sprite.index))
+                    .map(|rect| rect.size() * scale)
+            };
+        }
+    }
+
+    /// Squashes characters landing and stretches those taking off.
+    pub(crate) fn squash_and_stretch(mut query: Query<(&AnimationParams, &mut SpriteEffects)>) {
+        for (params, mut effects) in query.iter_mut() {
+            if params.landed() {
+                effects.squash(LANDING_SQUASH);
+            } else if params.jumped() {
+                effects.squash(JUMP_STRETCH);
+            }
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::on_update(AppState::RunningGame).with_system(SpriteEffects::apply)
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{
+    dungeon::Dungeon, Action, AppState, GameTime, InputBindings, Monster, Player, RogueLikePlugin,
+    RunSummary,
+};
+use bevy::{
+    asset::AssetPlugin,
+    ecs::event::Events,
+    hierarchy::HierarchyPlugin,
+    input::{keyboard::KeyboardInput, ElementState, InputPlugin},
+    prelude::*,
+    render::texture::ImagePlugin,
+    text::FontLoader,
+    transform::TransformPlugin,
+    utils::Instant,
+};
+use bevy_rapier2d::{plugin::TimestepMode, prelude::*};
+use std::{thread, time::Duration};
+
+const TIME_STEP: f32 = 1. / 60.;
+
+/// Longest wait for the assets to load or a run to start.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The game without a window or rendering: assets are loaded, but nothing is drawn.
+///
+/// Registers the asset types usually added by the render plugins, so that the loading screen,
+/// the atlases and the screens work the same as in the game. The `GameTime` and the physics
+/// are stepped by hand, by one fixed step each update.
+pub struct HeadlessPlugin;
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(AssetPlugin)
+            .add_plugin(ImagePlugin)
+            .add_asset::<TextureAtlas>()
+            .add_asset::<Mesh>()
+            .add_asset::<Font>()
+            .init_asset_loader::<FontLoader>()
+            // Without a primary window, the camera follows the player unclamped.
+            .init_resource::<Windows>()
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(8.))
+            .add_plugin(RogueLikePlugin)
+            .insert_resource(GameTime::manual())
+            .insert_resource(RapierConfiguration {
+                timestep_mode: TimestepMode::Fixed {
+                    dt: TIME_STEP,
+                    substeps: 1,
+                },
+                ..Default::default()
+            })
+            // Leaves out the bindings saved by the player.
+            .insert_resource(InputBindings::default());
+    }
+}
+
+/// Drives a `HeadlessPlugin` app tick by tick, such as from tests.
+///
+/// Each tick is one update of one fixed step, whatever the speed of the machine.
+pub struct HeadlessApp {
+    app: App,
+    ticks: u64,
+}
+impl HeadlessApp {
+    /// Loads the assets, stopping on the main menu.
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugin(HeadlessPlugin);
+
+        let mut headless = HeadlessApp { app, ticks: 0 };
+        headless.update_until(|headless| headless.state() != AppState::LoadingAssets);
+        if headless.state() == AppState::LoadingFailed {
+            let errors = headless.app.world.resource::<crate::AssetGroups>().errors();
+            panic!("Failed to load assets: {}", errors.join(", "));
+        }
+        headless
+    }
+
+    /// Starts a run on the first floor, generated from `seed`.
+    pub fn start_run(&mut self, seed: u64) {
+        self.app.world.insert_resource(RunSummary::new(seed));
+        self.app
+            .world
+            .resource_mut::<State<AppState>>()
+            .set(AppState::RunningGame)
+            .unwrap();
+        self.update_until(|headless| {
+            headless
+                .dungeon()
+                .is_some_and(|dungeon| dungeon.width() > 0)
+        });
+    }
+
+    /// Runs the game for one fixed step.
+    pub fn tick(&mut self) {
+        self.update();
+    }
+
+    /// Runs `ticks` fixed steps.
+    pub fn run(&mut self, ticks: u64) {
+        let end = self.ticks + ticks;
+        while self.ticks < end {
+            self.tick();
+        }
+    }
+
+    /// Runs until `condition` holds, for at most `ticks` fixed steps, returning whether it held.
+    pub fn run_until(&mut self, ticks: u64, condition: impl Fn(&mut Self) -> bool) -> bool {
+        let end = self.ticks + ticks;
+        while !condition(self) {
+            if self.ticks >= end {
+                return false;
+            }
+            self.tick();
+        }
+        true
+    }
+
+    /// Fixed steps run since the app started.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Holds the first key bound to `action` until released.
+    pub fn press(&mut self, action: Action) {
+        self.press_key(self.key(action));
+    }
+
+    pub fn release(&mut self, action: Action) {
+        self.release_key(self.key(action));
+    }
+
+    /// Holds `key`, seen by the game from the next update.
+    pub fn press_key(&mut self, key: KeyCode) {
+        self.send_key(key, ElementState::Pressed);
+    }
+
+    pub fn release_key(&mut self, key: KeyCode) {
+        self.send_key(key, ElementState::Released);
+    }
+
+    pub fn state(&self) -> AppState {
+        self.app
+            .world
+            .resource::<State<AppState>>()
+            .current()
+            .clone()
+    }
+
+    pub fn player(&mut self) -> Option<Entity> {
+        let mut query = self.app.world.query_filtered::<Entity, With<Player>>();
+        query.iter(&self.app.world).next()
+    }
+
+    pub fn monsters(&mut self) -> Vec<Entity> {
+        self.app
+            .world
+            .query_filtered::<Entity, With<Monster>>()
+            .iter(&self.app.world)
+            .collect()
+    }
+
+    pub fn dungeon(&mut self) -> Option<&Dungeon> {
+        let mut query = self.app.world.query::<&Dungeon>();
+        query.iter(&self.app.world).next()
+    }
+
+    /// Component `T` of `entity`, if it is still alive and has one.
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.app.world.get::<T>(entity)
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<Vec2> {
+        self.get::<Transform>(entity)
+            .map(|transform| transform.translation.truncate())
+    }
+
+    /// Sends `event`, read by the game from the next update.
+    pub fn send<E: Send + Sync + 'static>(&mut self, event: E) {
+        self.app.world.resource_mut::<Events<E>>().send(event);
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    fn key(&self, action: Action) -> KeyCode {
+        self.app
+            .world
+            .resource::<InputBindings>()
+            .key(action)
+            .unwrap_or_else(|| panic!("No key bound to {:?}", action))
+    }
+
+    fn send_key(&mut self, key: KeyCode, state: ElementState) {
+        self.send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key),
+            state,
+        });
+    }
+
+    fn update(&mut self) {
+        self.app
+            .world
+            .resource_mut::<GameTime>()
+            .advance(Duration::from_secs_f32(TIME_STEP));
+        self.app.update();
+        self.ticks += 1;
+    }
+
+    /// Updates until `condition` holds, waiting on the assets being loaded in the background.
+    fn update_until(&mut self, condition: impl Fn(&mut Self) -> bool) {
+        let start = Instant::now();
+        while !condition(self) {
+            if start.elapsed() > STARTUP_TIMEOUT {
+                panic!("Timed out in {:?}", self.state());
+            }
+            thread::sleep(Duration::from_millis(1));
+            self.update();
+        }
+    }
+}
+impl Default for HeadlessApp {
+    fn default() -> Self {
+        HeadlessApp::new()
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{
+    AnimationParams, Animator, AppState, GameTime, Player, ScreenShakeEvent, SpriteEffects,
+    Trigger, FLASH_RED, FLASH_WHITE,
+};
+use bevy::{prelude::*, utils::HashSet};
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+
+const INVULNERABILITY_DURATION: f32 = 1.;
+
+pub const DEATH_DURATION: f32 = 0.5;
+
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+}
+
+pub struct DeathEvent {
+    pub entity: Entity,
+}
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct Health {
+    current: f32,
+    max: f32,
+}
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Health { current: max, max }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.current / self.max
+    }
+
+    pub fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    /// Raises the maximum health by `amount` and fully heals.
+    pub fn raise_max(&mut self, amount: f32) {
+        self.max += amount;
+        self.current = self.max;
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
+
+    fn apply_damage(
+        mut commands: Commands,
+        mut damage_events: EventReader<DamageEvent>,
+        mut death_events: EventWriter<DeathEvent>,
+        mut shake_events: EventWriter<ScreenShakeEvent>,
+        mut query: Query<
+            (
+                &mut Health,
+                Option<&Player>,
+                Option<&mut AnimationParams>,
+                Option<&mut SpriteEffects>,
+            ),
+            (Without<Invulnerable>, Without<Dying>),
+        >,
+    ) {
+        // `Invulnerable` is only inserted once the stage ends, so later hits of the same update
+        // are skipped here.
+        let mut hit = HashSet::default();
+        for DamageEvent { target, amount } in damage_events.iter() {
+            let (mut health, player, params, effects) = match query.get_mut(*target) {
+                Ok(target) => target,
+                Err(_) => continue,
+            };
+            if health.is_dead() || !hit.insert(*target) {
+                continue;
+            }
+
+            let amount = match player {
+                Some(player) => {
+                    shake_events.send(ScreenShakeEvent {
+                        intensity: 4.,
+                        duration: 0.3,
+                    });
+                    player.reduce_damage(*amount)
+                }
+                None => *amount,
+            };
+            health.current = (health.current - amount).max(0.);
+
+            if let Some(mut effects) = effects {
+                effects.flash(if player.is_some() {
+                    FLASH_RED
+                } else {
+                    FLASH_WHITE
+                });
+                if !health.is_dead() {
+                    effects.blink(INVULNERABILITY_DURATION);
+                }
+            }
+
+            if health.is_dead() || !hit.insert(*target) {
+                death_events.send(DeathEvent { entity: *target });
+            } else {
+                if let Some(mut params) = params {
+                    params.trigger(Trigger::Hurt);
+                }
+                commands.entity(*target).insert(Invulnerable::default());
+            }
+        }
+    }
+
+    fn die(
+        mut commands: Commands,
+        mut death_events: EventReader<DeathEvent>,
+        mut query: Query<(
+            Option<&mut Animator>,
+            Option<&mut AnimationParams>,
+            Option<&DeathAnimation>,
+            Option<&DeathDuration>,
+            Option<&mut SpriteEffects>,
+        )>,
+    ) {
+        for DeathEvent { entity } in death_events.iter() {
+            if let Ok((animator, params, death_animation, death_duration, effects)) =
+                query.get_mut(*entity)
+            {
+                match (animator, params, death_animation) {
+                    (_, Some(mut params), _) => params.kill(),
+                    (Some(mut animator), None, Some(DeathAnimation(anim))) => {
+                        let flip_x = animator.flip_x();
+                        animator.change_animation(anim, flip_x);
+                    }
+                    _ => {}
+                }
+                let duration = death_duration.map_or(DEATH_DURATION, |duration| duration.0);
+                if let Some(mut effects) = effects {
+                    effects.fade_out(duration);
+                }
+                commands
+                    .entity(*entity)
+                    .remove::<Invulnerable>()
+                    .insert(Dying::new(duration));
+            }
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::on_update(AppState::RunningGame)
+            .with_system(Damage::deal_contact_damage)
+            .with_system(Health::apply_damage.after(Damage::deal_contact_damage))
+            .with_system(Health::die.after(Health::apply_damage))
+            .with_system(Invulnerable::expire.after(Health::die))
+            .with_system(Dying::despawn.after(Health::die))
+    }
+}
+
+/// Damage, death and contact damage, needed by the player and monsters alike.
+pub struct HealthPlugin;
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<DeathEvent>()
+            .add_event::<ScreenShakeEvent>()
+            .add_system_set(Health::system_set());
+    }
+}
+
+/// Damage dealt to the player when touching the holder.
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct Damage {
+    pub amount: f32,
+    /// Distance to the player under which the damage is dealt.
+    pub range: f32,
+}
+impl Damage {
+    fn deal_contact_damage(
+        attackers: Query<(&Damage, &Transform), (Without<Player>, Without<Dying>)>,
+        player: Query<(Entity, &Transform), (With<Player>, Without<Invulnerable>)>,
+        mut damage_events: EventWriter<DamageEvent>,
+    ) {
+        if player.is_empty() {
+            return;
+        }
+
+        let (player, player_transform) = player.single();
+
+        for (damage, transform) in attackers.iter() {
+            let distance = transform
+                .translation
+                .truncate()
+                .distance(player_transform.translation.truncate());
+            if distance <= damage.range {
+                damage_events.send(DamageEvent {
+                    target: player,
+                    amount: damage.amount,
+                });
+            }
+        }
+    }
+}
+
+/// Animation played by the `Animator` once the holder dies, for holders without an `AnimationGraph`.
+#[derive(Component)]
+pub struct DeathAnimation(pub &'static str);
+
+/// Seconds the holder lingers once dead, `DEATH_DURATION` if missing.
+#[derive(Component)]
+pub struct DeathDuration(pub f32);
+
+#[derive(Component)]
+pub struct Invulnerable {
+    timer: Timer,
+}
+impl Default for Invulnerable {
+    fn default() -> Self {
+        Invulnerable {
+            timer: Timer::from_seconds(INVULNERABILITY_DURATION, false),
+        }
+    }
+}
+impl Invulnerable {
+    fn expire(
+        mut commands: Commands,
+        time: Res<GameTime>,
+        mut query: Query<(Entity, &mut Invulnerable)>,
+    ) {
+        for (entity, mut invulnerable) in query.iter_mut() {
+            if invulnerable.timer.tick(time.delta()).finished() {
+                commands.entity(entity).remove::<Invulnerable>();
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Dying {
+    timer: Timer,
+}
+impl Default for Dying {
+    fn default() -> Self {
+        Dying::new(DEATH_DURATION)
+    }
+}
+impl Dying {
+    pub fn new(duration: f32) -> Self {
+        Dying {
+            timer: Timer::from_seconds(duration, false),
+        }
+    }
+
+    fn despawn(
+        mut commands: Commands,
+        time: Res<GameTime>,
+        mut query: Query<(Entity, &mut Dying)>,
+    ) {
+        for (entity, mut dying) in query.iter_mut() {
+            if dying.timer.tick(time.delta()).finished() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
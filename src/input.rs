@@ -0,0 +1,327 @@
+use crate::{AppState, Screen};
+use bevy::{prelude::*, utils::HashMap, utils::HashSet};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+const BINDINGS_PATH: &str = "bindings.ron";
+
+const STICK_DEADZONE: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Attack,
+    Dash,
+    Interact,
+    Pause,
+}
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Attack,
+        Action::Dash,
+        Action::Interact,
+        Action::Pause,
+    ];
+}
+
+/// Keyboard keys and gamepad buttons triggering each `Action`, saved to `BINDINGS_PATH`.
+#[derive(Serialize, Deserialize)]
+pub struct InputBindings {
+    keys: HashMap<Action, Vec<KeyCode>>,
+    buttons: HashMap<Action, Vec<GamepadButtonType>>,
+}
+impl Default for InputBindings {
+    fn default() -> Self {
+        InputBindings {
+            keys: HashMap::from_iter([
+                (Action::MoveLeft, vec![KeyCode::A, KeyCode::Left]),
+                (Action::MoveRight, vec![KeyCode::D, KeyCode::Right]),
+                (Action::Jump, vec![KeyCode::Space, KeyCode::W]),
+                (Action::Attack, vec![KeyCode::J]),
+                (Action::Dash, vec![KeyCode::LShift]),
+                (Action::Interact, vec![KeyCode::E]),
+                (Action::Pause, vec![KeyCode::Escape, KeyCode::P]),
+            ]),
+            buttons: HashMap::from_iter([
+                (Action::MoveLeft, vec![GamepadButtonType::DPadLeft]),
+                (Action::MoveRight, vec![GamepadButtonType::DPadRight]),
+                (Action::Jump, vec![GamepadButtonType::South]),
+                (Action::Attack, vec![GamepadButtonType::West]),
+                (Action::Dash, vec![GamepadButtonType::RightTrigger]),
+                (Action::Interact, vec![GamepadButtonType::North]),
+                (Action::Pause, vec![GamepadButtonType::Start]),
+            ]),
+        }
+    }
+}
+impl InputBindings {
+    pub fn load() -> Self {
+        InputBindings::load_from(Path::new(BINDINGS_PATH))
+    }
+
+    pub fn save(&self) {
+        self.save_to(Path::new(BINDINGS_PATH));
+    }
+
+    /// Bindings saved to `path`, the default ones if missing or invalid.
+    fn load_from(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(content) => ron::from_str(&content).unwrap_or_else(|e| {
+                warn!("Invalid {}, using default bindings: {}", path.display(), e);
+                InputBindings::default()
+            }),
+            Err(_) => InputBindings::default(),
+        }
+    }
+
+    fn save_to(&self, path: &Path) {
+        let content = ron::ser::to_string_pretty(self, Default::default()).unwrap();
+        if let Err(e) = fs::write(path, content) {
+            warn!("Could not save {}: {}", path.display(), e);
+        }
+    }
+
+    pub fn bind_key(&mut self, action: Action, key: KeyCode) {
+        self.keys.insert(action, vec![key]);
+    }
+
+    /// First key bound to `action`, if any.
+    pub fn key(&self, action: Action) -> Option<KeyCode> {
+        self.keys
+            .get(&action)
+            .and_then(|keys| keys.first())
+            .copied()
+    }
+
+    pub fn bind_button(&mut self, action: Action, button: GamepadButtonType) {
+        self.buttons.insert(action, vec![button]);
+    }
+
+    fn describe(&self, action: Action) -> String {
+        let keys = self.keys.get(&action).into_iter().flatten();
+        let buttons = self.buttons.get(&action).into_iter().flatten();
+        keys.map(|key| format!("{:?}", key))
+            .chain(buttons.map(|button| format!("Pad {:?}", button)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// `Action`s triggered this frame, from any bound keyboard key or gamepad input.
+#[derive(Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+}
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// Returns whether `action` was just pressed, so that it is handled only once per frame.
+    pub fn consume(&mut self, action: Action) -> bool {
+        self.just_pressed.remove(&action)
+    }
+
+    pub fn update(
+        mut action_state: ResMut<ActionState>,
+        bindings: Res<InputBindings>,
+        keyboard_input: Res<Input<KeyCode>>,
+        gamepads: Res<Gamepads>,
+        button_input: Res<Input<GamepadButton>>,
+        axes: Res<Axis<GamepadAxis>>,
+    ) {
+        let was_pressed = std::mem::take(&mut action_state.pressed);
+        action_state.just_pressed.clear();
+
+        for action in Action::ALL {
+            let key_pressed = bindings
+                .keys
+                .get(&action)
+                .into_iter()
+                .flatten()
+                .any(|key| keyboard_input.pressed(*key));
+
+            let button_pressed = gamepads.iter().any(|gamepad| {
+                bindings
+                    .buttons
+                    .get(&action)
+                    .into_iter()
+                    .flatten()
+                    .any(|button| button_input.pressed(GamepadButton(*gamepad, *button)))
+            });
+
+            let stick_pressed = gamepads.iter().any(|gamepad| {
+                let x = axes
+                    .get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickX))
+                    .unwrap_or(0.);
+                match action {
+                    Action::MoveLeft => x < -STICK_DEADZONE,
+                    Action::MoveRight => x > STICK_DEADZONE,
+                    _ => false,
+                }
+            });
+
+            if key_pressed || button_pressed || stick_pressed {
+                action_state.pressed.insert(action);
+                if !was_pressed.contains(&action) {
+                    action_state.just_pressed.insert(action);
+                }
+            }
+        }
+    }
+}
+
+/// Action waiting for a new binding in the controls screen.
+#[derive(Default)]
+pub struct Rebinding {
+    action: Option<Action>,
+}
+impl Rebinding {
+    pub fn controls(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        bindings: Res<InputBindings>,
+        rebinding: Res<Rebinding>,
+        screens: Query<Entity, With<Screen>>,
+    ) {
+        if !bindings.is_changed() && !rebinding.is_changed() {
+            return;
+        }
+
+        for entity in screens.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        let mut body = Action::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, action)| format!("{}: {:?} [{}]", i + 1, action, bindings.describe(*action)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        match rebinding.action {
+            Some(action) => body += &format!("\n\nPress a key or button for {:?}", action),
+            None => body += "\n\n1-7: rebind action\nC: back",
+        }
+        Screen::spawn(&mut commands, &asset_server, "Controls", body);
+    }
+
+    pub fn rebind(
+        mut rebinding: ResMut<Rebinding>,
+        mut bindings: ResMut<InputBindings>,
+        mut state: ResMut<State<AppState>>,
+        mut keyboard_input: ResMut<Input<KeyCode>>,
+        button_input: Res<Input<GamepadButton>>,
+    ) {
+        match rebinding.action {
+            Some(action) => {
+                let key = keyboard_input.get_just_pressed().next().copied();
+                if let Some(key) = key {
+                    keyboard_input.clear();
+                    bindings.bind_key(action, key);
+                } else if let Some(GamepadButton(_, button)) =
+                    button_input.get_just_pressed().next().copied()
+                {
+                    bindings.bind_button(action, button);
+                } else {
+                    return;
+                }
+                bindings.save();
+                rebinding.action = None;
+            }
+            None => {
+                const ACTION_KEYS: [KeyCode; 7] = [
+                    KeyCode::Key1,
+                    KeyCode::Key2,
+                    KeyCode::Key3,
+                    KeyCode::Key4,
+                    KeyCode::Key5,
+                    KeyCode::Key6,
+                    KeyCode::Key7,
+                ];
+
+                if keyboard_input.just_pressed(KeyCode::C) {
+                    keyboard_input.clear();
+                    let _ = state.pop();
+                } else if let Some(i) = ACTION_KEYS
+                    .iter()
+                    .position(|key| keyboard_input.just_pressed(*key))
+                {
+                    keyboard_input.clear();
+                    rebinding.action = Some(Action::ALL[i]);
+                }
+            }
+        }
+    }
+
+    pub fn open(mut keyboard_input: ResMut<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+        if keyboard_input.just_pressed(KeyCode::C) {
+            keyboard_input.clear();
+            let _ = state.push(AppState::Controls);
+        }
+    }
+
+    pub fn reset(mut rebinding: ResMut<Rebinding>) {
+        rebinding.action = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// File of the test named `name`, removed beforehand.
+    fn bindings_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("rogue_like_{}_bindings.ron", name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn saves_and_loads_rebound_actions() {
+        let path = bindings_file("round_trip");
+        let mut bindings = InputBindings::default();
+        bindings.bind_key(Action::Attack, KeyCode::K);
+        bindings.bind_button(Action::Jump, GamepadButtonType::East);
+        bindings.save_to(&path);
+
+        let loaded = InputBindings::load_from(&path);
+        for action in Action::ALL {
+            assert_eq!(loaded.describe(action), bindings.describe(action));
+        }
+        assert_eq!(loaded.key(Action::Attack), Some(KeyCode::K));
+        assert_eq!(loaded.describe(Action::Jump), "Space, W, Pad East");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn defaults_when_missing_or_invalid() {
+        let path = bindings_file("invalid");
+        let defaults = InputBindings::default();
+        assert_eq!(
+            InputBindings::load_from(&path).key(Action::Attack),
+            defaults.key(Action::Attack)
+        );
+
+        fs::write(&path, "(keys: {").unwrap();
+        let loaded = InputBindings::load_from(&path);
+        for action in Action::ALL {
+            assert_eq!(loaded.describe(action), defaults.describe(action));
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn binds_every_action_by_default() {
+        let bindings = InputBindings::default();
+        for action in Action::ALL {
+            assert!(bindings.key(action).is_some(), "{:?} unbound", action);
+        }
+    }
+}
@@ -0,0 +1,333 @@
+mod loot;
+
+use crate::{
+    dungeon::TerrainTile, Action, ActionState, Animator, AppState, Dying, Health, Player,
+    SpriteSheet,
+};
+use bevy::{prelude::*, utils::HashMap};
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+use bevy_rapier2d::prelude::*;
+pub use loot::*;
+use serde::Deserialize;
+
+const TIME_STEP: f32 = 1. / 60.;
+
+const PICKUP_RANGE: f32 = 16.;
+
+const SCALE: f32 = 0.15;
+
+const HALF_SIZE: f32 = 80. * SCALE;
+
+const GRAVITY: f32 = 240.;
+
+/// Fraction of the speed kept after hitting the terrain.
+const RESTITUTION: f32 = 0.5;
+
+/// Speed under which a bouncing item lying on the ground comes to rest.
+const REST_SPEED: f32 = 20.;
+
+const HEAL_AMOUNT: f32 = 3.;
+
+const SPECIAL_MAX_HEALTH: f32 = 2.;
+
+const CHEST_SCALE: f32 = 1.25;
+
+/// Distance under which the player can open a chest.
+const CHEST_RANGE: f32 = 20.;
+
+const IDLE_ANIMATION: &str = "idle";
+
+const CHEST_CLOSED_ANIMATION: &str = "closed";
+
+const CHEST_OPEN_ANIMATION: &str = "open";
+
+/// Items, chests and the loot dropped by monsters.
+///
+/// Needs the `HealthPlugin` and the `AnimationPlugin`.
+pub struct ItemPlugin;
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LootTables>()
+            .init_resource::<LootRng>()
+            .add_system_set(Item::system_set())
+            .add_system_set(Item::loot_system_set());
+    }
+}
+
+#[derive(Bundle)]
+pub struct ItemBundle {
+    #[bundle]
+    sprite_bundle: SpriteSheetBundle,
+    item: Item,
+    animator: Animator,
+}
+impl ItemBundle {
+    pub fn new(sheet: &SpriteSheet, kind: ItemKind, at: Vec3) -> Self {
+        ItemBundle {
+            sprite_bundle: SpriteSheetBundle {
+                texture_atlas: sheet.atlas.clone(),
+                transform: Transform {
+                    translation: at,
+                    scale: Vec3::new(SCALE, SCALE, 1.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            item: Item { kind },
+            animator: Animator::new(vec![(
+                IDLE_ANIMATION,
+                sheet.animation(IDLE_ANIMATION).ping_pong(),
+            )]),
+        }
+    }
+}
+
+pub struct ItemAtlases {
+    items: HashMap<ItemKind, SpriteSheet>,
+    pub chest: SpriteSheet,
+}
+impl ItemAtlases {
+    pub fn new(chest: SpriteSheet) -> Self {
+        ItemAtlases {
+            items: HashMap::default(),
+            chest,
+        }
+    }
+
+    pub fn insert(&mut self, kind: ItemKind, sheet: SpriteSheet) {
+        self.items.insert(kind, sheet);
+    }
+
+    pub fn get(&self, kind: ItemKind) -> &SpriteSheet {
+        &self.items[&kind]
+    }
+}
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum ItemKind {
+    /// Heals the player.
+    Hp,
+    /// Adds a piece of armor.
+    #[default]
+    Defence,
+    /// Sharpens the sword.
+    Axe,
+    /// Lengthens the reach of the sword.
+    Scepter,
+    /// Raises the maximum health of the player and fully heals it.
+    Special,
+}
+impl ItemKind {
+    pub const ALL: [ItemKind; 5] = [
+        ItemKind::Hp,
+        ItemKind::Defence,
+        ItemKind::Axe,
+        ItemKind::Scepter,
+        ItemKind::Special,
+    ];
+
+    /// Name of the sprite group in `assets/data/manifest.ron`.
+    pub fn sprites(&self) -> &'static str {
+        match self {
+            ItemKind::Hp => "items/hp",
+            ItemKind::Defence => "items/defence",
+            ItemKind::Axe => "items/axe",
+            ItemKind::Scepter => "items/scepter",
+            ItemKind::Special => "items/special",
+        }
+    }
+}
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component, Default)]
+pub struct Item {
+    kind: ItemKind,
+}
+impl Item {
+    fn pick_up(
+        mut commands: Commands,
+        items: Query<(Entity, &Item, &Transform)>,
+        mut player: Query<(&mut Player, &mut Health, &Transform)>,
+    ) {
+        if player.is_empty() {
+            return;
+        }
+
+        let (mut player, mut health, player_transform) = player.single_mut();
+
+        for (entity, item, transform) in items.iter() {
+            let distance = transform
+                .translation
+                .truncate()
+                .distance(player_transform.translation.truncate());
+            if distance > PICKUP_RANGE {
+                continue;
+            }
+
+            match item.kind {
+                ItemKind::Hp => health.heal(HEAL_AMOUNT),
+                ItemKind::Defence => player.add_defence(),
+                ItemKind::Axe => player.sharpen(),
+                ItemKind::Scepter => player.extend_reach(),
+                ItemKind::Special => health.raise_max(SPECIAL_MAX_HEALTH),
+            }
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::new()
+            .with_run_criteria(AppState::fixed_step(TIME_STEP))
+            .with_system(Item::pick_up)
+            .with_system(Bounce::fall)
+    }
+
+    /// Reacts to deaths and pressed actions, which only last a frame or two, so would be missed
+    /// by the fixed steps at high frame rates.
+    pub fn loot_system_set() -> SystemSet {
+        SystemSet::on_update(AppState::RunningGame)
+            .with_system(Loot::drop_loot)
+            .with_system(Chest::open)
+    }
+}
+
+/// Throws a dropped item in the air, bouncing on the terrain until it comes to rest.
+#[derive(Component)]
+pub struct Bounce {
+    velocity: Vec2,
+}
+impl Bounce {
+    pub fn new(velocity: Vec2) -> Self {
+        Bounce { velocity }
+    }
+
+    fn fall(
+        mut commands: Commands,
+        rapier_context: Res<RapierContext>,
+        terrain: Query<(), With<TerrainTile>>,
+        mut query: Query<(Entity, &mut Bounce, &mut Transform)>,
+    ) {
+        let is_terrain = |entity| terrain.get(entity).is_ok();
+
+        for (entity, mut bounce, mut transform) in query.iter_mut() {
+            bounce.velocity.y -= GRAVITY * TIME_STEP;
+            let step = bounce.velocity * TIME_STEP;
+            let distance = step.length();
+            if distance <= f32::EPSILON {
+                continue;
+            }
+
+            let hit = rapier_context.cast_ray_and_get_normal(
+                transform.translation.truncate(),
+                step / distance,
+                distance + HALF_SIZE,
+                true,
+                InteractionGroups::all(),
+                Some(&is_terrain),
+            );
+            match hit {
+                Some((_, intersection)) => {
+                    let normal = intersection.normal;
+                    let velocity = bounce.velocity;
+                    bounce.velocity = (velocity - 2. * velocity.dot(normal) * normal) * RESTITUTION;
+                    if normal.y > 0.5 && bounce.velocity.length() < REST_SPEED {
+                        commands.entity(entity).remove::<Bounce>();
+                    }
+                }
+                None => transform.translation += step.extend(0.),
+            }
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct ChestBundle {
+    #[bundle]
+    sprite_bundle: SpriteSheetBundle,
+    chest: Chest,
+    loot: Loot,
+    animator: Animator,
+    collider: Collider,
+    rigidbody: RigidBody,
+    locked_axis: LockedAxes,
+}
+impl ChestBundle {
+    /// Closed chest, dropping loot from the chest table once opened.
+    pub fn new(sheet: &SpriteSheet, at: Vec3) -> Self {
+        ChestBundle {
+            sprite_bundle: SpriteSheetBundle {
+                texture_atlas: sheet.atlas.clone(),
+                transform: Transform {
+                    translation: at,
+                    scale: Vec3::new(CHEST_SCALE, CHEST_SCALE, 1.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            chest: Chest,
+            loot: Loot::new(CHEST_LOOT),
+            animator: Animator::new(vec![
+                (
+                    CHEST_CLOSED_ANIMATION,
+                    sheet.animation(CHEST_CLOSED_ANIMATION),
+                ),
+                (
+                    CHEST_OPEN_ANIMATION,
+                    sheet.animation(CHEST_OPEN_ANIMATION).hold_last(),
+                ),
+            ]),
+            collider: Collider::cuboid(7., 6.),
+            rigidbody: RigidBody::Dynamic,
+            locked_axis: LockedAxes::ROTATION_LOCKED,
+        }
+    }
+}
+
+/// Prop opened by the player, keeping its `Loot` until then.
+#[derive(Component)]
+pub struct Chest;
+impl Chest {
+    #[allow(clippy::too_many_arguments)]
+    fn open(
+        mut commands: Commands,
+        mut action_state: ResMut<ActionState>,
+        tables: Res<LootTables>,
+        atlases: Res<ItemAtlases>,
+        mut rng: ResMut<LootRng>,
+        player: Query<&Transform, (With<Player>, Without<Dying>)>,
+        mut chests: Query<(Entity, &Loot, &Transform, &mut Animator), With<Chest>>,
+    ) {
+        let player = match player.get_single() {
+            Ok(transform) => transform.translation.truncate(),
+            Err(_) => return,
+        };
+
+        let nearest = chests
+            .iter()
+            .map(|(entity, _, transform, _)| {
+                (entity, transform.translation.truncate().distance(player))
+            })
+            .filter(|(_, distance)| *distance <= CHEST_RANGE)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+        let chest = match nearest {
+            Some((entity, _)) if action_state.consume(Action::Interact) => chests.get_mut(entity),
+            _ => return,
+        };
+        let (entity, loot, transform, mut animator) = match chest {
+            Ok(chest) => chest,
+            Err(_) => return,
+        };
+
+        animator.change_animation(CHEST_OPEN_ANIMATION, false);
+        loot.drop(
+            &mut commands,
+            &tables,
+            &atlases,
+            &mut rng,
+            transform.translation,
+        );
+        commands.entity(entity).remove::<Loot>();
+    }
+}
@@ -0,0 +1,198 @@
+use super::{Bounce, ItemAtlases, ItemBundle, ItemKind};
+use crate::DeathEvent;
+use bevy::prelude::*;
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const TABLES: &str = include_str!("../../assets/data/loot.ron");
+
+/// Name of the drop table used by chests.
+pub const CHEST_LOOT: &str = "chest";
+
+/// Vertical speed given to a dropped item.
+const DROP_IMPULSE: f32 = 90.;
+
+/// Largest horizontal speed given to a dropped item.
+const DROP_SPREAD: f32 = 30.;
+
+#[derive(Deserialize)]
+struct Drop {
+    item: ItemKind,
+    weight: u32,
+}
+
+#[derive(Deserialize)]
+struct LootTable {
+    /// Probability of dropping anything at all.
+    chance: f32,
+    drops: Vec<Drop>,
+}
+impl LootTable {
+    fn roll(&self, rng: &mut StdRng) -> Option<ItemKind> {
+        if self.drops.is_empty() || !rng.gen_bool(self.chance.clamp(0., 1.) as f64) {
+            return None;
+        }
+        let index = WeightedIndex::new(self.drops.iter().map(|drop| drop.weight)).ok()?;
+        Some(self.drops[index.sample(rng)].item)
+    }
+}
+
+/// Drop tables by name, read from `assets/data/loot.ron`.
+pub struct LootTables {
+    tables: HashMap<String, LootTable>,
+}
+impl Default for LootTables {
+    fn default() -> Self {
+        LootTables {
+            tables: ron::from_str(TABLES).expect("Invalid loot tables"),
+        }
+    }
+}
+
+/// Random generator of the drops, seeded with the floor so a run replays identically.
+pub struct LootRng(StdRng);
+impl Default for LootRng {
+    fn default() -> Self {
+        LootRng::new(0)
+    }
+}
+impl LootRng {
+    pub fn new(seed: u64) -> Self {
+        LootRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+/// Drop table rolled once the holder dies, or once a chest is opened.
+#[derive(Component)]
+pub struct Loot {
+    table: String,
+}
+impl Loot {
+    pub fn new(table: &str) -> Self {
+        Loot {
+            table: table.to_string(),
+        }
+    }
+
+    pub(super) fn drop_loot(
+        mut commands: Commands,
+        tables: Res<LootTables>,
+        atlases: Res<ItemAtlases>,
+        mut rng: ResMut<LootRng>,
+        mut death_events: EventReader<DeathEvent>,
+        query: Query<(&Loot, &Transform)>,
+    ) {
+        for DeathEvent { entity } in death_events.iter() {
+            if let Ok((loot, transform)) = query.get(*entity) {
+                loot.drop(
+                    &mut commands,
+                    &tables,
+                    &atlases,
+                    &mut rng,
+                    transform.translation,
+                );
+            }
+        }
+    }
+
+    /// Rolls the drop table, throwing the item dropped, if any, from `at`.
+    pub(super) fn drop(
+        &self,
+        commands: &mut Commands,
+        tables: &LootTables,
+        atlases: &ItemAtlases,
+        rng: &mut LootRng,
+        at: Vec3,
+    ) {
+        let kind = match tables.tables.get(&self.table) {
+            Some(table) => table.roll(&mut rng.0),
+            None => {
+                warn!("Unknown loot table {}", self.table);
+                return;
+            }
+        };
+
+        if let Some(kind) = kind {
+            let velocity = Vec2::new(rng.0.gen_range(-DROP_SPREAD..=DROP_SPREAD), DROP_IMPULSE);
+            commands
+                .spawn_bundle(ItemBundle::new(
+                    atlases.get(kind),
+                    kind,
+                    at.truncate().extend(1.),
+                ))
+                .insert(Bounce::new(velocity))
+                .insert(Name::new(format!("{:?} Item", kind)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(chance: f32, drops: &[(ItemKind, u32)]) -> LootTable {
+        LootTable {
+            chance,
+            drops: drops
+                .iter()
+                .map(|&(item, weight)| Drop { item, weight })
+                .collect(),
+        }
+    }
+
+    fn rolls(table: &LootTable, seed: u64) -> Vec<Option<ItemKind>> {
+        let mut rng = LootRng::new(seed);
+        (0..1000).map(|_| table.roll(&mut rng.0)).collect()
+    }
+
+    #[test]
+    fn replays_the_same_seed() {
+        let table = table(0.5, &[(ItemKind::Hp, 3), (ItemKind::Axe, 1)]);
+        assert_eq!(rolls(&table, 7), rolls(&table, 7));
+        assert_ne!(rolls(&table, 7), rolls(&table, 8));
+    }
+
+    #[test]
+    fn drops_by_chance() {
+        let never = table(0., &[(ItemKind::Hp, 1)]);
+        assert!(rolls(&never, 0).iter().all(Option::is_none));
+
+        let always = table(1., &[(ItemKind::Hp, 1)]);
+        assert!(rolls(&always, 0)
+            .iter()
+            .all(|&kind| kind == Some(ItemKind::Hp)));
+
+        let empty = table(1., &[]);
+        assert!(rolls(&empty, 0).iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn drops_by_weight() {
+        let table = table(
+            1.,
+            &[
+                (ItemKind::Hp, 3),
+                (ItemKind::Axe, 1),
+                (ItemKind::Scepter, 0),
+            ],
+        );
+        let rolls = rolls(&table, 0);
+        let count = |kind| rolls.iter().filter(|&&roll| roll == Some(kind)).count();
+
+        assert_eq!(count(ItemKind::Scepter), 0);
+        assert!(
+            (650..850).contains(&count(ItemKind::Hp)),
+            "{}",
+            count(ItemKind::Hp)
+        );
+        assert_eq!(count(ItemKind::Hp) + count(ItemKind::Axe), rolls.len());
+    }
+
+    #[test]
+    fn reads_the_drop_tables() {
+        let tables = LootTables::default();
+        assert!(tables.tables.contains_key(CHEST_LOOT));
+    }
+}
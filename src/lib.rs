@@ -0,0 +1,285 @@
+// Bevy systems spell out their queries, of several components and filters, in their parameters.
+#![allow(clippy::type_complexity)]
+
+mod animator;
+mod boss;
+mod camera;
+pub mod dungeon;
+mod effects;
+mod headless;
+mod health;
+mod input;
+mod item;
+mod loading;
+mod monster;
+mod player;
+mod run;
+
+pub use animator::*;
+// Shadows the animation plugin of the Bevy prelude.
+pub use animator::AnimationPlugin;
+use bevy::prelude::*;
+pub use boss::*;
+pub use camera::*;
+pub use dungeon::DungeonPlugin;
+pub use effects::*;
+pub use headless::*;
+pub use health::*;
+pub use input::*;
+pub use item::*;
+pub use loading::*;
+pub use monster::*;
+pub use player::*;
+pub use run::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AppState {
+    LoadingAssets,
+    LoadingFailed,
+    MainMenu,
+    RunningGame,
+    Descending,
+    Paused,
+    Controls,
+    GameOver,
+    Victory,
+}
+
+/// The whole game, from the loading screen to the victory screen.
+///
+/// Leaves the window, rendering and physics plugins to the app, along with debugging tools.
+pub struct RogueLikePlugin;
+impl Plugin for RogueLikePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(AnimationPlugin)
+            .add_plugin(HealthPlugin)
+            .add_plugin(DungeonPlugin)
+            .add_plugin(PlayerPlugin)
+            .add_plugin(MonsterPlugin)
+            .add_plugin(ItemPlugin)
+            .add_plugin(BossPlugin)
+            // Resources
+            .init_resource::<AssetManifest>()
+            .init_resource::<AssetGroups>()
+            .init_resource::<RunSummary>()
+            .init_resource::<GameTime>()
+            .add_state(AppState::LoadingAssets)
+            // Startup
+            .add_system_set(AssetGroups::load_system_set())
+            .add_startup_system(spawn_cameras)
+            .add_system_set(AssetGroups::system_set())
+            .add_system_set(
+                SystemSet::on_exit(AppState::LoadingAssets)
+                    .with_system(build_texture_atlases)
+                    .with_system(Screen::despawn),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::RunningGame).with_system(setup))
+            .add_system_set(RunSummary::teardown_system_set())
+            .add_system_set(RunSummary::descend_system_set())
+            .add_system_set(CameraController::reset_system_set())
+            // Screens
+            .add_system_set(
+                SystemSet::on_enter(AppState::LoadingFailed).with_system(Screen::loading_failed),
+            )
+            .add_system_set(SystemSet::on_update(AppState::LoadingFailed).with_system(Screen::quit))
+            .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(Screen::main_menu))
+            .add_system_set(
+                SystemSet::on_update(AppState::MainMenu)
+                    .with_system(Screen::start_run)
+                    .with_system(Screen::quit),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(Screen::despawn))
+            .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(Screen::paused))
+            .add_system_set(
+                SystemSet::on_update(AppState::Paused)
+                    .with_system(Screen::resume)
+                    .with_system(Rebinding::open)
+                    .with_system(Screen::back_to_main_menu),
+            )
+            .add_system_set(SystemSet::on_resume(AppState::Paused).with_system(Screen::paused))
+            .add_system_set(SystemSet::on_exit(AppState::Paused).with_system(Screen::despawn))
+            .add_system_set(SystemSet::on_enter(AppState::Controls).with_system(Rebinding::reset))
+            .add_system_set(
+                SystemSet::on_update(AppState::Controls)
+                    .with_system(Rebinding::controls)
+                    .with_system(Rebinding::rebind),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Controls).with_system(Screen::despawn))
+            .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(Screen::game_over))
+            .add_system_set(
+                SystemSet::on_update(AppState::GameOver)
+                    .with_system(Screen::start_run)
+                    .with_system(Screen::back_to_main_menu),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::GameOver).with_system(Screen::despawn))
+            .add_system_set(SystemSet::on_enter(AppState::Victory).with_system(Screen::victory))
+            .add_system_set(
+                SystemSet::on_update(AppState::Victory)
+                    .with_system(Screen::start_run)
+                    .with_system(Screen::back_to_main_menu),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Victory).with_system(Screen::despawn))
+            // Systems
+            .add_system_to_stage(CoreStage::First, GameTime::follow)
+            .add_system(AppState::freeze_physics)
+            .add_system_set(RunSummary::system_set())
+            .add_system_set(CameraController::system_set());
+    }
+}
+
+fn build_texture_atlases(
+    mut commands: Commands,
+    groups: Res<AssetGroups>,
+    asset_server: Res<AssetServer>,
+    archetypes: Res<MonsterArchetypes>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut textures: ResMut<Assets<Image>>,
+) {
+    if !groups.errors().is_empty() {
+        return;
+    }
+
+    // Terrain
+    let terrain_atlas =
+        TextureAtlas::from_grid(groups.first("terrain"), Vec2::new(16., 16.), 13, 8);
+    commands.insert_resource(dungeon::DungeonAtlases {
+        terrain: texture_atlases.add(terrain_atlas),
+    });
+
+    let mut build = |group: &str| match groups.grid(group) {
+        Some(grid) => SpriteSheet::from_grid(groups.first(group), grid, &mut texture_atlases),
+        None => SpriteSheet::build(
+            groups.get(group),
+            &asset_server,
+            &mut textures,
+            &mut texture_atlases,
+        ),
+    };
+    let animations = |name: &str| asset_server.load(&format!("animations/{}.anim.ron", name));
+
+    // Player
+    commands.insert_resource(PlayerAtlases {
+        defence: (0..=MAX_DEFENCE)
+            .map(|defence| {
+                build(&format!("player/defence{}", defence)).with_definition(animations("player"))
+            })
+            .collect(),
+    });
+
+    // Monsters
+    let mut monster_atlases = MonsterAtlases::default();
+    for archetype in archetypes.iter() {
+        monster_atlases.insert(
+            archetype,
+            build(&archetype.sprites).with_definition(animations(&archetype.animations)),
+        );
+    }
+    commands.insert_resource(monster_atlases);
+
+    // Items
+    let mut item_atlases = ItemAtlases::new(build("props/chest"));
+    for kind in ItemKind::ALL {
+        item_atlases.insert(kind, build(kind.sprites()));
+    }
+    commands.insert_resource(item_atlases);
+
+    // Bosses
+    commands.insert_resource(BossAtlases {
+        slime_king: build("bosses/slime_king").with_definition(animations("slime_king")),
+        slime_door: build("doors/slime"),
+        goblin_king: build("bosses/goblin_king").with_definition(animations("goblin_king")),
+        goblin_king_projectile: build("bosses/goblin_king_projectile"),
+        goblin_door: build("doors/goblin"),
+        skeleton_king: build("bosses/skeleton_king").with_definition(animations("skeleton_king")),
+        skeleton_door: build("doors/skeleton"),
+        dungeon_master: build("bosses/dungeon_master")
+            .with_definition(animations("dungeon_master")),
+        dungeon_master_projectiles: (0..3)
+            .map(|i| build(&format!("bosses/dungeon_master_projectile{}", i)))
+            .collect(),
+        dungeon_master_door: build("doors/dungeon_master"),
+    });
+}
+
+fn spawn_cameras(mut commands: Commands) {
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(CameraController::default());
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands.spawn_bundle(Text2dBundle::default());
+}
+
+#[allow(clippy::too_many_arguments)]
+fn setup(
+    mut commands: Commands,
+    dungeon_atlases: Res<dungeon::DungeonAtlases>,
+    player_atlases: Res<PlayerAtlases>,
+    archetypes: Res<MonsterArchetypes>,
+    monster_atlases: Res<MonsterAtlases>,
+    item_atlases: Res<ItemAtlases>,
+    boss_atlases: Res<BossAtlases>,
+    summary: Res<RunSummary>,
+) {
+    let seed = summary.seed.wrapping_add(summary.depth as u64);
+    commands.insert_resource(LootRng::new(seed));
+
+    // Terrain
+    let dungeon = dungeon::Dungeon::new(dungeon_atlases.terrain.clone(), seed);
+    let flying_monster_spawns = dungeon.flying_monster_spawns();
+    let chest_spawns = dungeon.chest_spawns();
+    commands
+        .spawn_bundle(SpriteBundle::default())
+        .insert(dungeon)
+        .insert(Name::new("Dungeon"));
+
+    // Player
+    commands
+        .spawn_bundle(PlayerBundle::new(&player_atlases.defence[0]))
+        .insert(Name::new("Player"));
+
+    // Monsters
+    for (i, (species, variant)) in [
+        (Species::Slime, 0),
+        (Species::Goblin, 1),
+        (Species::Skeleton, 0),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let archetype = archetypes.get(species, variant).unwrap();
+        commands
+            .spawn_bundle(MonsterBundle::from_archetype(
+                archetype,
+                monster_atlases.get(archetype),
+                Vec3::new(16. * (10. + 3. * i as f32), 16. * 6. * 3. + 10., 0.),
+            ))
+            .insert(Name::new(format!("{:?}", species)));
+    }
+    let bird = archetypes.get(Species::Bird, 0).unwrap();
+    for at in flying_monster_spawns {
+        commands
+            .spawn_bundle(MonsterBundle::from_archetype(
+                bird,
+                monster_atlases.get(bird),
+                at.extend(0.),
+            ))
+            .insert(Name::new("Bird"));
+    }
+
+    // Items
+    commands
+        .spawn_bundle(ItemBundle::new(
+            item_atlases.get(ItemKind::Defence),
+            ItemKind::Defence,
+            Vec3::new(16. * 6., 16. * 6. * 3. + 10., 0.),
+        ))
+        .insert(Name::new("Defence Item"));
+    for at in chest_spawns {
+        commands
+            .spawn_bundle(ChestBundle::new(&item_atlases.chest, at.extend(0.)))
+            .insert(Name::new("Chest"));
+    }
+
+    // Boss
+    Boss::spawn_for_floor(&mut commands, &boss_atlases, summary.depth);
+}
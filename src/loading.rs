@@ -0,0 +1,257 @@
+use crate::{AppState, Screen, FONT};
+use bevy::{
+    asset::{AssetServerSettings, FileAssetIo, LoadState},
+    prelude::*,
+    utils::HashMap,
+};
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+/// Path of the manifest, inside the asset folder.
+const MANIFEST_PATH: &str = "data/manifest.ron";
+
+#[derive(Deserialize)]
+enum AssetSource {
+    /// Every asset of the folder, along with those of its subfolders.
+    Folder(String),
+    /// Assets directly inside the folder, leaving out those of its subfolders.
+    Sprites(String),
+    File(String),
+    /// A single image cut into the frames of `grid`.
+    Grid {
+        path: String,
+        grid: SpriteGrid,
+    },
+}
+
+/// Frames of an image laid out on a grid, such as a sheet of many small creatures.
+#[derive(Deserialize, Clone)]
+pub struct SpriteGrid {
+    /// Width and height of each frame, in pixels.
+    pub tile: f32,
+    pub columns: usize,
+    pub rows: usize,
+    /// Frames of each animation, counted row by row from the top left.
+    pub animations: HashMap<String, Vec<usize>>,
+}
+
+#[derive(Deserialize)]
+struct AssetGroup {
+    name: String,
+    source: AssetSource,
+}
+
+/// Asset groups to load before the main menu, read from `assets/data/manifest.ron`.
+pub struct AssetManifest {
+    /// Groups of the manifest, or why it could not be read, shown by the loading error screen.
+    groups: Result<Vec<AssetGroup>, String>,
+}
+impl FromWorld for AssetManifest {
+    fn from_world(world: &mut World) -> Self {
+        let folder = world.get_resource::<AssetServerSettings>().map_or_else(
+            || AssetServerSettings::default().asset_folder,
+            |settings| settings.asset_folder.clone(),
+        );
+        let path = FileAssetIo::get_root_path()
+            .join(folder)
+            .join(MANIFEST_PATH);
+
+        let groups = fs::read_to_string(&path)
+            .map_err(|e| format!("{}: {}", path.display(), e))
+            .and_then(|content| {
+                ron::from_str(&content).map_err(|e| format!("{}: {}", MANIFEST_PATH, e))
+            });
+        AssetManifest { groups }
+    }
+}
+
+/// Handles of the assets of the manifest, by group name.
+#[derive(Default)]
+pub struct AssetGroups {
+    groups: HashMap<String, Vec<HandleUntyped>>,
+    grids: HashMap<String, SpriteGrid>,
+    /// Groups which could not start loading, such as missing folders, then assets which failed.
+    errors: Vec<String>,
+}
+impl AssetGroups {
+    /// Handles of the group `name`, empty if the manifest has no such group.
+    pub fn get(&self, name: &str) -> &[HandleUntyped] {
+        match self.groups.get(name) {
+            Some(handles) => handles,
+            None => {
+                warn!("Unknown asset group {}", name);
+                &[]
+            }
+        }
+    }
+
+    /// First asset of the group `name`, such as the only one of a `File` group.
+    pub fn first<T: bevy::asset::Asset>(&self, name: &str) -> Handle<T> {
+        self.get(name)
+            .first()
+            .map(|handle| handle.clone().typed())
+            .unwrap_or_default()
+    }
+
+    /// Layout of the group `name`, if it is a `Grid` group.
+    pub fn grid(&self, name: &str) -> Option<&SpriteGrid> {
+        self.grids.get(name)
+    }
+
+    /// Groups and assets which failed to load.
+    pub fn errors(&self) -> &[String] {
+        &self.errors
+    }
+
+    fn handles(&self) -> impl Iterator<Item = &HandleUntyped> {
+        self.groups.values().flatten()
+    }
+
+    fn load(
+        mut groups: ResMut<AssetGroups>,
+        manifest: Res<AssetManifest>,
+        asset_server: Res<AssetServer>,
+    ) {
+        *groups = AssetGroups::default();
+
+        let manifest = match &manifest.groups {
+            Ok(manifest) => manifest,
+            Err(error) => {
+                groups.errors.push(error.clone());
+                return;
+            }
+        };
+        for AssetGroup { name, source } in manifest {
+            let handles = match source {
+                AssetSource::Folder(folder) => asset_server.load_folder(folder),
+                AssetSource::Sprites(folder) => asset_server.load_folder(folder).map(|handles| {
+                    handles
+                        .into_iter()
+                        .filter(|handle| {
+                            asset_server
+                                .get_handle_path(handle.id)
+                                .is_some_and(|path| path.path().parent() == Some(Path::new(folder)))
+                        })
+                        .collect()
+                }),
+                AssetSource::File(path) => Ok(vec![asset_server.load_untyped(path.as_str())]),
+                AssetSource::Grid { path, grid } => {
+                    groups.grids.insert(name.clone(), grid.clone());
+                    Ok(vec![asset_server.load_untyped(path.as_str())])
+                }
+            };
+
+            match handles {
+                Ok(handles) if handles.is_empty() => {
+                    groups.errors.push(format!("{}: no assets found", name));
+                }
+                Ok(handles) => {
+                    groups.groups.insert(name.clone(), handles);
+                }
+                Err(error) => groups.errors.push(format!("{}: {}", name, error)),
+            }
+        }
+    }
+
+    /// Fills the progress bar, moving on to the main menu once every asset is loaded.
+    fn check(
+        mut groups: ResMut<AssetGroups>,
+        mut state: ResMut<State<AppState>>,
+        asset_server: Res<AssetServer>,
+        mut bar: Query<&mut Style, With<LoadingBar>>,
+    ) {
+        let mut loaded = 0;
+        let mut failed = vec![];
+        for handle in groups.handles() {
+            match asset_server.get_load_state(handle.id) {
+                LoadState::Loaded => loaded += 1,
+                LoadState::Failed => failed.push(
+                    asset_server
+                        .get_handle_path(handle.id)
+                        .map_or(format!("{:?}", handle.id), |path| {
+                            path.path().display().to_string()
+                        }),
+                ),
+                _ => {}
+            }
+        }
+        let total = groups.handles().count();
+
+        for mut style in bar.iter_mut() {
+            style.size.width = Val::Percent(100. * loaded as f32 / total.max(1) as f32);
+        }
+
+        groups.errors.extend(failed);
+        if !groups.errors.is_empty() {
+            state.set(AppState::LoadingFailed).unwrap();
+        } else if loaded == total {
+            state.set(AppState::MainMenu).unwrap();
+        }
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::on_update(AppState::LoadingAssets).with_system(AssetGroups::check)
+    }
+
+    pub fn load_system_set() -> SystemSet {
+        SystemSet::on_enter(AppState::LoadingAssets)
+            .with_system(AssetGroups::load)
+            .with_system(LoadingBar::spawn)
+    }
+}
+
+/// Filling part of the progress bar of the loading screen.
+#[derive(Component)]
+pub struct LoadingBar;
+impl LoadingBar {
+    fn spawn(mut commands: Commands, asset_server: Res<AssetServer>) {
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    flex_direction: FlexDirection::ColumnReverse,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                color: Color::BLACK.into(),
+                ..Default::default()
+            })
+            .insert(Screen)
+            .with_children(|screen| {
+                screen.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        "Loading",
+                        TextStyle {
+                            font: asset_server.load(FONT),
+                            font_size: 40.,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                });
+                screen
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(400.), Val::Px(16.)),
+                            margin: Rect::all(Val::Px(16.)),
+                            ..Default::default()
+                        },
+                        color: Color::DARK_GRAY.into(),
+                        ..Default::default()
+                    })
+                    .with_children(|bar| {
+                        bar.spawn_bundle(NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(0.), Val::Percent(100.)),
+                                ..Default::default()
+                            },
+                            color: Color::WHITE.into(),
+                            ..Default::default()
+                        })
+                        .insert(LoadingBar);
+                    });
+            });
+    }
+}
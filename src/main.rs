@@ -1,17 +1,20 @@
-mod animator;
-mod dungeon;
-mod monster;
-mod player;
-
-use animator::*;
-use bevy::{asset::LoadState, prelude::*};
+#[cfg(feature = "dev")]
+use bevy::asset::AssetServerSettings;
+use bevy::prelude::*;
+#[cfg(feature = "dev")]
 use bevy_inspector_egui::{RegisterInspectable, WorldInspectorPlugin};
 use bevy_rapier2d::prelude::*;
-use monster::*;
-use player::*;
+use rogue_like::*;
 
 fn main() {
-    App::new()
+    let mut app = App::new();
+    // Hot reloads the assets, read by the asset plugin so inserted before it.
+    #[cfg(feature = "dev")]
+    app.insert_resource(AssetServerSettings {
+        watch_for_changes: true,
+        ..default()
+    });
+    app
         // Builtins
         .insert_resource(Msaa { samples: 4 })
         .insert_resource(WindowDescriptor {
@@ -19,103 +22,28 @@ fn main() {
             ..default()
         })
         .add_plugins(DefaultPlugins)
-        .add_plugin(WorldInspectorPlugin::new())
-        .add_plugin(RapierDebugRenderPlugin::default())
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(8.))
-        // Resources
-        .init_resource::<SpriteHandles>()
-        .add_state(AppState::LoadingGameSprites)
-        // Startup
-        .add_system_set(
-            SystemSet::on_enter(AppState::LoadingGameSprites).with_system(load_textures),
-        )
-        .add_system_set(
-            SystemSet::on_update(AppState::LoadingGameSprites).with_system(check_loading_textures),
-        )
-        .add_system_set(SystemSet::on_enter(AppState::RunningGame).with_system(setup))
-        // Systems
-        .add_system_set(Animator::system_set())
-        .add_system_set(Player::system_set())
-        .add_system_set(dungeon::Dungeon::system_set())
-        .add_system(bevy::input::system::exit_on_esc_system)
-        // Inspect
+        // Game
+        .add_plugin(RogueLikePlugin);
+
+    #[cfg(feature = "dev")]
+    app.add_plugin(WorldInspectorPlugin::new())
+        .add_plugin(RapierDebugRenderPlugin::default())
         .register_inspectable::<Player>()
         .register_inspectable::<Monster>()
+        .register_inspectable::<Perception>()
+        .register_inspectable::<Item>()
+        .register_inspectable::<Health>()
+        .register_inspectable::<Damage>()
+        .register_inspectable::<Boss>()
+        .register_inspectable::<SlimeKing>()
+        .register_inspectable::<GoblinKing>()
+        .register_inspectable::<SkeletonKing>()
+        .register_inspectable::<DungeonMaster>()
         .register_inspectable::<Animator>()
-        // Run
-        .run();
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum AppState {
-    LoadingGameSprites,
-    RunningGame,
-}
-
-#[derive(Default)]
-struct SpriteHandles {
-    player: Vec<HandleUntyped>,
-    slime: Vec<HandleUntyped>,
-    terrain: Option<Handle<Image>>,
-}
-
-fn load_textures(mut sprite_handles: ResMut<SpriteHandles>, asset_server: Res<AssetServer>) {
-    *sprite_handles = SpriteHandles {
-        player: asset_server
-            .load_folder("RoguelikeDungeon/Sprites/Player/Sword/Defence0")
-            .unwrap(),
-        slime: asset_server
-            .load_folder("RoguelikeDungeon/Sprites/Monsters/Slime/Variant0")
-            .unwrap(),
-        terrain: Some(asset_server.load("Dungeon/Terrain/Dungeon_Terrain_Tileset.png")),
-    }
-}
-
-fn check_loading_textures(
-    mut state: ResMut<State<AppState>>,
-    sprite_handles: ResMut<SpriteHandles>,
-    asset_server: Res<AssetServer>,
-) {
-    let player =
-        asset_server.get_group_load_state(sprite_handles.player.iter().map(|handle| handle.id));
-    let slime =
-        asset_server.get_group_load_state(sprite_handles.slime.iter().map(|handle| handle.id));
-
-    match (player, slime) {
-        (LoadState::Loaded, LoadState::Loaded) => state.set(AppState::RunningGame).unwrap(),
-        _ => {}
-    }
-}
-
-fn setup(
-    mut commands: Commands,
-    sprite_handles: ResMut<SpriteHandles>,
-    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
-    mut textures: ResMut<Assets<Image>>,
-) {
-    // Terrain
-    let terrain_handle = sprite_handles.terrain.clone().unwrap();
-    let terrain_atlas = TextureAtlas::from_grid(terrain_handle, Vec2::new(16., 16.), 13, 8);
-    let terrain_atlas_handle = texture_atlases.add(terrain_atlas);
-    commands
-        .spawn_bundle(SpriteBundle::default())
-        .insert(dungeon::Dungeon::new(terrain_atlas_handle))
-        .insert(Name::new("Dungeon"));
-
-    // Player
-    let mut player_atlas_builder = TextureAtlasBuilder::default();
-
-    for handle in &sprite_handles.player {
-        let texture = textures.get(handle).unwrap();
-        player_atlas_builder.add_texture(handle.clone_weak().typed::<Image>(), texture);
-    }
-    let player_atlas = player_atlas_builder.finish(&mut textures).unwrap();
-    let player_atlas_handle = texture_atlases.add(player_atlas);
-    commands
-        .spawn_bundle(PlayerBundle::new(player_atlas_handle))
-        .insert(Name::new("Player"));
+        .register_inspectable::<AnimationGraph>()
+        .register_inspectable::<AnimationParams>()
+        .register_inspectable::<CameraController>();
 
-    // Others
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
-    commands.spawn_bundle(Text2dBundle::default());
+    app.run();
 }
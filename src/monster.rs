@@ -1,28 +1,441 @@
-use bevy::{core::FixedTimestep, prelude::*};
+mod archetype;
+mod perception;
+
+use crate::{
+    dungeon::{Move, Path, PathRequest, PathStep, TerrainTile},
+    AnimationGraph, AnimationParams, AnimationSource, Animator, AppState, Damage, Dying, GameTime,
+    Health, Loot, SpriteEffects, SpriteSheet,
+};
+pub use archetype::*;
+use bevy::prelude::*;
+#[cfg(feature = "dev")]
 use bevy_inspector_egui::Inspectable;
+use bevy_rapier2d::prelude::*;
+pub use perception::*;
+use std::time::Duration;
+
+const TIME_STEP: f32 = 1. / 60.;
+
+/// Half the width and height of every monster's collider, whatever the scale of its sprites.
+const HALF_SIZE: f32 = 12.8;
+
+const CONTACT_RANGE: f32 = 12.;
+
+const ATTACK_DURATION: f32 = 0.4;
+
+const RETREAT_DURATION: f32 = 0.8;
+
+/// Seconds between two path requests of a chasing ground monster.
+const REPATH_INTERVAL: f32 = 0.5;
+
+/// Vertical speed given to walking monsters jumping along their path.
+const JUMP_IMPULSE: f32 = 80.;
+
+/// Length of the rays used by flying monsters to steer around terrain.
+const FEELER_LENGTH: f32 = HALF_SIZE + 12.;
+
+/// Angles, in radians, tried in order when the direct path of a flying monster is blocked.
+const FEELER_ANGLES: [f32; 7] = [0., 0.5, -0.5, 1., -1., 1.6, -1.6];
+
+/// Monsters, from their archetypes to their behaviour.
+///
+/// Needs the `HealthPlugin`, the `AnimationPlugin` and the `DungeonPlugin` for pathfinding.
+/// Their loot is dropped by the `ItemPlugin`.
+pub struct MonsterPlugin;
+impl Plugin for MonsterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MonsterArchetypes>()
+            .add_system_set(Monster::system_set());
+    }
+}
 
 #[derive(Bundle)]
 pub struct MonsterBundle {
     #[bundle]
     sprite_bundle: SpriteSheetBundle,
-    player: Monster,
+    monster: Monster,
+    perception: Perception,
+    loot: Loot,
+    animator: Animator,
+    animation_source: AnimationSource,
+    animation_graph: AnimationGraph,
+    animation_params: AnimationParams,
+    effects: SpriteEffects,
+    health: Health,
+    damage: Damage,
+    collider: Collider,
+    rigidbody: RigidBody,
+    velocity: Velocity,
+    gravity: GravityScale,
+    locked_axis: LockedAxes,
 }
 impl MonsterBundle {
-    pub fn new(atlas: Handle<TextureAtlas>) -> Self {
+    pub fn from_archetype(archetype: &Archetype, sheet: &SpriteSheet, at: Vec3) -> Self {
+        let (r, g, b) = archetype.tint;
+
         MonsterBundle {
             sprite_bundle: SpriteSheetBundle {
-                texture_atlas: atlas,
+                texture_atlas: sheet.atlas.clone(),
+                sprite: TextureAtlasSprite {
+                    color: Color::rgb(r, g, b),
+                    ..Default::default()
+                },
+                transform: Transform {
+                    translation: at,
+                    scale: Vec3::new(archetype.scale, archetype.scale, 1.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            monster: Monster {
+                species: archetype.species,
+                speed: archetype.speed,
+                attack_range: archetype.attack_range,
+                movement: archetype.movement,
+                home: at.truncate(),
                 ..Default::default()
             },
-            player: Monster {},
+            perception: Perception::new(
+                archetype.sight_range,
+                archetype.view_angle,
+                archetype.memory,
+            ),
+            loot: Loot::new(&archetype.loot),
+            animator: Animator::default(),
+            animation_source: AnimationSource::new(sheet),
+            animation_graph: AnimationGraph::character(),
+            animation_params: AnimationParams::default(),
+            effects: SpriteEffects::default(),
+            health: Health::new(archetype.health),
+            damage: Damage {
+                amount: archetype.damage,
+                range: CONTACT_RANGE,
+            },
+            collider: Collider::cuboid(HALF_SIZE / archetype.scale, HALF_SIZE / archetype.scale),
+            rigidbody: RigidBody::Dynamic,
+            velocity: Velocity::default(),
+            gravity: GravityScale(match archetype.movement {
+                Movement::Fly { .. } => 0.,
+                _ => 1.,
+            }),
+            locked_axis: LockedAxes::ROTATION_LOCKED,
         }
     }
 }
 
-#[derive(Inspectable, Component)]
-pub struct Monster {}
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Behaviour {
+    /// Walks back and forth between ledges and walls.
+    #[default]
+    Patrol,
+    /// Walks toward the player, stopping at ledges.
+    Chase,
+    /// Lunges at the player.
+    Attack,
+    /// Backs off after an attack before chasing again.
+    Retreat,
+}
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct Monster {
+    species: Species,
+    behaviour: Behaviour,
+    speed: f32,
+    attack_range: f32,
+    movement: Movement,
+    home: Vec2,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    direction: f32,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    timer: Timer,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    hop: Timer,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    repath: Timer,
+}
+impl Default for Monster {
+    fn default() -> Self {
+        Monster {
+            species: Species::Slime,
+            behaviour: Behaviour::Patrol,
+            speed: 30.,
+            attack_range: 24.,
+            movement: Movement::Walk,
+            home: Vec2::ZERO,
+            direction: 1.,
+            timer: Timer::from_seconds(0., false),
+            hop: Timer::from_seconds(0., false),
+            repath: Timer::from_seconds(0., false),
+        }
+    }
+}
 impl Monster {
+    pub fn species(&self) -> Species {
+        self.species
+    }
+
+    fn change_behaviour(&mut self, behaviour: Behaviour, duration: f32) {
+        self.behaviour = behaviour;
+        self.timer = Timer::from_seconds(duration, false);
+    }
+
+    fn think(
+        mut path_requests: EventWriter<PathRequest>,
+        mut monsters: Query<(Entity, &mut Monster, &Perception, &Transform), Without<Dying>>,
+    ) {
+        for (entity, mut monster, perception, transform) in monsters.iter_mut() {
+            monster.timer.tick(Duration::from_secs_f32(TIME_STEP));
+            monster.hop.tick(Duration::from_secs_f32(TIME_STEP));
+            monster.repath.tick(Duration::from_secs_f32(TIME_STEP));
+
+            let position = transform.translation.truncate();
+            let target = perception.target();
+            let (distance, toward_target) = match target {
+                Some(target) => (position.distance(target), (target.x - position.x).signum()),
+                None => (f32::INFINITY, monster.direction),
+            };
+
+            match monster.behaviour {
+                Behaviour::Patrol => {
+                    if perception.sees_player() {
+                        monster.change_behaviour(Behaviour::Chase, 0.);
+                    }
+                }
+                Behaviour::Chase => {
+                    monster.direction = toward_target;
+                    let walks = !matches!(monster.movement, Movement::Fly { .. });
+                    if walks && monster.repath.finished() {
+                        if let Some(goal) = target {
+                            monster.repath = Timer::from_seconds(REPATH_INTERVAL, false);
+                            path_requests.send(PathRequest { entity, goal });
+                        }
+                    }
+                    if target.is_none() {
+                        monster.change_behaviour(Behaviour::Patrol, 0.);
+                    } else if perception.sees_player() && distance <= monster.attack_range {
+                        monster.change_behaviour(Behaviour::Attack, ATTACK_DURATION);
+                    }
+                }
+                Behaviour::Attack => {
+                    if monster.timer.finished() {
+                        monster.direction = -toward_target;
+                        monster.change_behaviour(Behaviour::Retreat, RETREAT_DURATION);
+                    }
+                }
+                Behaviour::Retreat => {
+                    if monster.timer.finished() {
+                        monster.change_behaviour(Behaviour::Chase, 0.);
+                    }
+                }
+            }
+        }
+    }
+
+    fn act(
+        rapier_context: Res<RapierContext>,
+        terrain: Query<(), With<TerrainTile>>,
+        mut monsters: Query<(
+            &mut Monster,
+            &Transform,
+            &mut Velocity,
+            &mut AnimationParams,
+            Option<&mut Path>,
+            Option<&Dying>,
+        )>,
+    ) {
+        let is_terrain = |entity| terrain.get(entity).is_ok();
+
+        for (mut monster, transform, mut velocity, mut params, path, dying) in monsters.iter_mut() {
+            if dying.is_some() {
+                velocity.linvel.x = 0.;
+                continue;
+            }
+            if let Movement::Fly { .. } = monster.movement {
+                continue;
+            }
+
+            let position = transform.translation.truncate();
+            let step = match (monster.behaviour, path) {
+                (Behaviour::Chase, Some(mut path)) => path.next(position),
+                _ => None,
+            };
+            if let Some(step) = step {
+                if (step.position.x - position.x).abs() > 1. {
+                    monster.direction = (step.position.x - position.x).signum();
+                }
+            }
+
+            let ahead = Vec2::new(monster.direction, 0.);
+            let wall = rapier_context
+                .cast_ray(
+                    position,
+                    ahead,
+                    HALF_SIZE + 2.,
+                    true,
+                    InteractionGroups::all(),
+                    Some(&is_terrain),
+                )
+                .is_some();
+            let ground = rapier_context
+                .cast_ray(
+                    position + ahead * (HALF_SIZE + 2.),
+                    -Vec2::Y,
+                    HALF_SIZE + 4.,
+                    true,
+                    InteractionGroups::all(),
+                    Some(&is_terrain),
+                )
+                .is_some();
+            let grounded = rapier_context
+                .cast_ray(
+                    position,
+                    -Vec2::Y,
+                    HALF_SIZE + 1.,
+                    true,
+                    InteractionGroups::all(),
+                    Some(&is_terrain),
+                )
+                .is_some();
+            let blocked = match step.map(|step| step.movement) {
+                Some(Move::Jump) => false,
+                Some(Move::Drop) => wall,
+                _ => wall || !ground,
+            };
+
+            let speed = match monster.behaviour {
+                Behaviour::Patrol if blocked => {
+                    monster.direction = -monster.direction;
+                    0.
+                }
+                _ if blocked => 0.,
+                Behaviour::Patrol => monster.speed,
+                Behaviour::Chase => monster.speed * 1.5,
+                Behaviour::Attack => monster.speed * 3.,
+                Behaviour::Retreat => monster.speed,
+            };
+
+            match monster.movement {
+                Movement::Walk => {
+                    velocity.linvel.x = monster.direction * speed;
+                    if let Some(PathStep {
+                        position: target,
+                        movement: Move::Jump,
+                    }) = step
+                    {
+                        if grounded && (wall || !ground || target.y > position.y) {
+                            velocity.linvel.y = JUMP_IMPULSE;
+                        }
+                    }
+                }
+                Movement::Hop { impulse, interval } => {
+                    if grounded {
+                        velocity.linvel.x = 0.;
+                        if speed > 0. && monster.hop.finished() {
+                            velocity.linvel = Vec2::new(monster.direction * speed * 2., impulse);
+                            monster.hop = Timer::from_seconds(interval, false);
+                        }
+                    }
+                }
+                Movement::Fly { .. } => {}
+            }
+
+            params.face(monster.direction);
+        }
+    }
+
+    fn fly(
+        time: Res<GameTime>,
+        rapier_context: Res<RapierContext>,
+        terrain: Query<(), With<TerrainTile>>,
+        mut monsters: Query<
+            (
+                &Monster,
+                &Perception,
+                &Transform,
+                &mut Velocity,
+                &mut AnimationParams,
+            ),
+            Without<Dying>,
+        >,
+    ) {
+        let is_terrain = |entity| terrain.get(entity).is_ok();
+        let t = time.seconds_since_startup() as f32;
+
+        for (monster, perception, transform, mut velocity, mut params) in monsters.iter_mut() {
+            let hover_radius = match monster.movement {
+                Movement::Fly { hover_radius } => hover_radius,
+                _ => continue,
+            };
+
+            let position = transform.translation.truncate();
+            let (target, speed) = match (monster.behaviour, perception.target()) {
+                (Behaviour::Chase, Some(target)) => (target, monster.speed * 1.5),
+                (Behaviour::Attack, Some(target)) => (target, monster.speed * 3.),
+                (Behaviour::Retreat, Some(target)) => (
+                    position + (position - target).normalize_or_zero() * FEELER_LENGTH,
+                    monster.speed,
+                ),
+                _ => (
+                    monster.home + Vec2::new(t.cos(), (2. * t).sin() / 2.) * hover_radius,
+                    monster.speed,
+                ),
+            };
+
+            let to_target = target - position;
+            let desired = to_target.normalize_or_zero() * speed.min(to_target.length() * 4.);
+            velocity.linvel = steer(&rapier_context, &is_terrain, position, desired);
+
+            if velocity.linvel.x.abs() > f32::EPSILON {
+                params.face(velocity.linvel.x);
+            }
+        }
+    }
+
     pub fn system_set() -> SystemSet {
-        SystemSet::new().with_run_criteria(FixedTimestep::step(1. / 60.))
+        SystemSet::new()
+            .with_run_criteria(AppState::fixed_step(TIME_STEP))
+            .with_system(Perception::perceive)
+            .with_system(Monster::think.after(Perception::perceive))
+            .with_system(Monster::act.after(Monster::think))
+            .with_system(Monster::fly.after(Monster::think))
+    }
+}
+
+/// Turns `desired` velocity toward the closest direction free of terrain.
+fn steer(
+    rapier_context: &RapierContext,
+    is_terrain: &dyn Fn(Entity) -> bool,
+    position: Vec2,
+    desired: Vec2,
+) -> Vec2 {
+    let speed = desired.length();
+    if speed <= f32::EPSILON {
+        return Vec2::ZERO;
     }
+
+    let direction = desired / speed;
+    FEELER_ANGLES
+        .iter()
+        .map(|angle| {
+            let (sin, cos) = angle.sin_cos();
+            Vec2::new(
+                direction.x * cos - direction.y * sin,
+                direction.x * sin + direction.y * cos,
+            )
+        })
+        .find(|feeler| {
+            rapier_context
+                .cast_ray(
+                    position,
+                    *feeler,
+                    FEELER_LENGTH,
+                    true,
+                    InteractionGroups::all(),
+                    Some(is_terrain),
+                )
+                .is_none()
+        })
+        .map_or(Vec2::ZERO, |feeler| feeler * speed)
 }
@@ -0,0 +1,91 @@
+use crate::SpriteSheet;
+use bevy::utils::HashMap;
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+use serde::Deserialize;
+
+const ARCHETYPES: &str = include_str!("../../assets/data/monsters.ron");
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Species {
+    #[default]
+    Slime,
+    Goblin,
+    Skeleton,
+    Bird,
+}
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Movement {
+    /// Moves continuously along the ground.
+    #[default]
+    Walk,
+    /// Only moves while airborne, jumping every `interval` seconds.
+    Hop { impulse: f32, interval: f32 },
+    /// Ignores gravity, hovering around its spawn point until it swoops at the player.
+    Fly { hover_radius: f32 },
+}
+/// Stats and sprites shared by every monster of a species variant.
+#[derive(Deserialize, Clone)]
+pub struct Archetype {
+    pub species: Species,
+    pub variant: usize,
+    /// Name of the sprite group in `assets/data/manifest.ron`.
+    pub sprites: String,
+    /// Name of the animation definitions in `assets/animations`.
+    pub animations: String,
+    /// Scale of the sprites, which aren't all drawn at the same size.
+    pub scale: f32,
+    pub health: f32,
+    pub speed: f32,
+    pub damage: f32,
+    pub sight_range: f32,
+    /// Half angle, in radians, of the view cone.
+    pub view_angle: f32,
+    /// Seconds the player is remembered once out of sight.
+    pub memory: f32,
+    pub attack_range: f32,
+    pub movement: Movement,
+    pub tint: (f32, f32, f32),
+    /// Name of the drop table in `assets/data/loot.ron`.
+    pub loot: String,
+}
+
+pub struct MonsterArchetypes {
+    archetypes: Vec<Archetype>,
+}
+impl Default for MonsterArchetypes {
+    fn default() -> Self {
+        MonsterArchetypes {
+            archetypes: ron::from_str(ARCHETYPES).expect("Invalid monster archetypes"),
+        }
+    }
+}
+impl MonsterArchetypes {
+    pub fn iter(&self) -> impl Iterator<Item = &Archetype> {
+        self.archetypes.iter()
+    }
+
+    pub fn get(&self, species: Species, variant: usize) -> Option<&Archetype> {
+        self.archetypes
+            .iter()
+            .find(|archetype| archetype.species == species && archetype.variant == variant)
+    }
+}
+
+#[derive(Default)]
+pub struct MonsterAtlases {
+    sheets: HashMap<(Species, usize), SpriteSheet>,
+}
+impl MonsterAtlases {
+    pub fn insert(&mut self, archetype: &Archetype, sheet: SpriteSheet) {
+        self.sheets
+            .insert((archetype.species, archetype.variant), sheet);
+    }
+
+    pub fn get(&self, archetype: &Archetype) -> &SpriteSheet {
+        &self.sheets[&(archetype.species, archetype.variant)]
+    }
+}
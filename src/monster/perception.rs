@@ -0,0 +1,92 @@
+use super::TIME_STEP;
+use crate::{dungeon::TerrainTile, Animator, Player};
+use bevy::prelude::*;
+#[cfg(feature = "dev")]
+use bevy_inspector_egui::Inspectable;
+use bevy_rapier2d::prelude::*;
+use std::time::Duration;
+
+/// What a monster knows of the player, from an unobstructed view cone.
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct Perception {
+    /// Distance up to which the player can be seen.
+    view_range: f32,
+    /// Half angle, in radians, of the view cone around the facing direction.
+    view_angle: f32,
+    /// Seconds the last seen position of the player is remembered once out of sight.
+    memory: f32,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    sees_player: bool,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    last_seen: Option<Vec2>,
+    #[cfg_attr(feature = "dev", inspectable(ignore))]
+    forget: Timer,
+}
+impl Perception {
+    pub fn new(view_range: f32, view_angle: f32, memory: f32) -> Self {
+        Perception {
+            view_range,
+            view_angle,
+            memory,
+            sees_player: false,
+            last_seen: None,
+            forget: Timer::from_seconds(0., false),
+        }
+    }
+
+    pub fn sees_player(&self) -> bool {
+        self.sees_player
+    }
+
+    /// Position of the player when last seen, until forgotten.
+    pub fn target(&self) -> Option<Vec2> {
+        self.last_seen
+    }
+
+    pub fn perceive(
+        rapier_context: Res<RapierContext>,
+        terrain: Query<(), With<TerrainTile>>,
+        player: Query<&Transform, With<Player>>,
+        mut query: Query<(&mut Perception, &Transform, Option<&Animator>), Without<Player>>,
+    ) {
+        let is_terrain = |entity| terrain.get(entity).is_ok();
+        let player = player.get_single().ok().map(|t| t.translation.truncate());
+
+        for (mut perception, transform, animator) in query.iter_mut() {
+            let position = transform.translation.truncate();
+            let facing = match animator {
+                Some(animator) if animator.flip_x() => -Vec2::X,
+                _ => Vec2::X,
+            };
+
+            perception.sees_player = player.is_some_and(|player| {
+                let to_player = player - position;
+                let distance = to_player.length();
+                distance <= perception.view_range
+                    && facing.angle_between(to_player).abs() <= perception.view_angle
+                    && rapier_context
+                        .cast_ray(
+                            position,
+                            to_player / distance,
+                            distance,
+                            true,
+                            InteractionGroups::all(),
+                            Some(&is_terrain),
+                        )
+                        .is_none()
+            });
+
+            if perception.sees_player {
+                perception.last_seen = player;
+                perception.forget = Timer::from_seconds(perception.memory, false);
+            } else if perception
+                .forget
+                .tick(Duration::from_secs_f32(TIME_STEP))
+                .finished()
+            {
+                perception.last_seen = None;
+            }
+        }
+    }
+}
@@ -1,5 +1,10 @@
-use crate::{Animator, AppState};
-use bevy::{core::FixedTimestep, prelude::*, sprite::Anchor};
+use crate::{
+    Action, ActionState, AnimationEvent, AnimationGraph, AnimationParams, AnimationSource,
+    AnimationState, Animator, AppState, DamageEvent, Dying, Health, InputBindings, Rebinding,
+    SpriteEffects, SpriteSheet, Trigger,
+};
+use bevy::{input::InputSystem, prelude::*};
+#[cfg(feature = "dev")]
 use bevy_inspector_egui::Inspectable;
 use bevy_rapier2d::prelude::*;
 
@@ -9,6 +14,11 @@ pub struct PlayerBundle {
     sprite_bundle: SpriteSheetBundle,
     player: Player,
     animator: Animator,
+    animation_source: AnimationSource,
+    animation_graph: AnimationGraph,
+    animation_params: AnimationParams,
+    effects: SpriteEffects,
+    health: Health,
     collider: Collider,
     rigidbody: RigidBody,
     locked_axis: LockedAxes,
@@ -19,6 +29,11 @@ impl Default for PlayerBundle {
             sprite_bundle: SpriteSheetBundle::default(),
             player: Player::default(),
             animator: Animator::default(),
+            animation_source: AnimationSource::default(),
+            animation_graph: AnimationGraph::character(),
+            animation_params: AnimationParams::default(),
+            effects: SpriteEffects::default(),
+            health: Health::new(MAX_HEALTH),
             collider: Collider::cuboid(32., 32.),
             rigidbody: RigidBody::Dynamic,
             locked_axis: LockedAxes::ROTATION_LOCKED,
@@ -26,14 +41,10 @@ impl Default for PlayerBundle {
     }
 }
 impl PlayerBundle {
-    pub fn new(atlas: Handle<TextureAtlas>) -> Self {
+    pub fn new(sheet: &SpriteSheet) -> Self {
         PlayerBundle {
             sprite_bundle: SpriteSheetBundle {
-                texture_atlas: atlas,
-                sprite: TextureAtlasSprite {
-                    anchor: Anchor::Custom(Vec2::new(0., -0.28)),
-                    ..Default::default()
-                },
+                texture_atlas: sheet.atlas.clone(),
                 transform: Transform {
                     translation: Vec3::new(16., 16. * 6. * 3. + 10., 0.),
                     scale: Vec3::new(0.4, 0.4, 1.),
@@ -41,18 +52,109 @@ impl PlayerBundle {
                 },
                 ..Default::default()
             },
-            animator: Animator::new(vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9, 10, 11]]),
+            animation_source: AnimationSource::new(sheet),
             ..Default::default()
         }
     }
 }
 
-#[derive(Inspectable, Component, Default)]
-pub struct Player {}
+const TIME_STEP: f32 = 1. / 60.;
+
+const ATTACK_ANIMATION: &str = "attack";
+
+const MAX_HEALTH: f32 = 10.;
+
+const ATTACK_DAMAGE: f32 = 1.;
+
+/// Horizontal reach of a sword swing, in front of the player.
+const ATTACK_RANGE: f32 = 32.;
+
+const ATTACK_HEIGHT: f32 = 24.;
+
+/// Tag of the attack frame on which the sword strikes, in `assets/animations/player.anim.ron`.
+const HIT_EVENT: &str = "hit active";
+
+/// Damage added to the sword by each axe picked up.
+const SHARPEN_DAMAGE: f32 = 0.5;
+
+const MAX_ATTACK_DAMAGE: f32 = 3.;
+
+/// Reach added to the sword by each scepter picked up.
+const EXTEND_RANGE: f32 = 8.;
+
+const MAX_ATTACK_RANGE: f32 = 64.;
+
+pub const MAX_DEFENCE: usize = 4;
+
+const DEFENCE_REDUCTION: f32 = 0.15;
+
+/// The player and the actions driving it, along with their bindings.
+///
+/// Needs the `HealthPlugin` and the `AnimationPlugin`.
+pub struct PlayerPlugin;
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>()
+            .init_resource::<Rebinding>()
+            .insert_resource(InputBindings::load())
+            .add_system_to_stage(CoreStage::PreUpdate, ActionState::update.after(InputSystem))
+            .add_system_set(Player::system_set())
+            .add_system_set(Player::strike_system_set());
+    }
+}
+
+pub struct PlayerAtlases {
+    pub defence: Vec<SpriteSheet>,
+}
+
+#[cfg_attr(feature = "dev", derive(Inspectable))]
+#[derive(Component)]
+pub struct Player {
+    #[cfg_attr(feature = "dev", inspectable(max = 4))]
+    defence: usize,
+    attack_damage: f32,
+    attack_range: f32,
+}
+impl Default for Player {
+    fn default() -> Self {
+        Player {
+            defence: 0,
+            attack_damage: ATTACK_DAMAGE,
+            attack_range: ATTACK_RANGE,
+        }
+    }
+}
 impl Player {
+    pub fn add_defence(&mut self) {
+        self.defence = (self.defence + 1).min(MAX_DEFENCE);
+    }
+
+    pub fn sharpen(&mut self) {
+        self.attack_damage = (self.attack_damage + SHARPEN_DAMAGE).min(MAX_ATTACK_DAMAGE);
+    }
+
+    pub fn extend_reach(&mut self) {
+        self.attack_range = (self.attack_range + EXTEND_RANGE).min(MAX_ATTACK_RANGE);
+    }
+
+    pub fn reduce_damage(&self, damage: f32) -> f32 {
+        damage * (1. - DEFENCE_REDUCTION * self.defence as f32)
+    }
+
+    fn update_armor(
+        atlases: Res<PlayerAtlases>,
+        mut query: Query<(&Player, &mut AnimationSource), Changed<Player>>,
+    ) {
+        for (player, mut source) in query.iter_mut() {
+            if let Some(sheet) = atlases.defence.get(player.defence) {
+                source.set_sheet(sheet);
+            }
+        }
+    }
+
     fn move_player(
-        keyboard_input: Res<Input<KeyCode>>,
-        mut query: Query<(&mut Transform, &mut Animator), With<Player>>,
+        action_state: Res<ActionState>,
+        mut query: Query<(&mut Transform, &mut AnimationParams), (With<Player>, Without<Dying>)>,
     ) {
         if query.is_empty() {
             return;
@@ -60,30 +162,79 @@ impl Player {
 
         const SPEED: f32 = 5.;
 
-        let (mut transform, mut animator) = query.single_mut();
-
-        let mut has_moved = false;
+        let (mut transform, mut params) = query.single_mut();
 
-        if keyboard_input.pressed(KeyCode::D) {
-            has_moved = true;
+        if action_state.pressed(Action::MoveRight) {
             transform.translation.x += SPEED;
-            animator.change_animation(2, false);
+            params.face(1.);
         }
 
-        if keyboard_input.pressed(KeyCode::A) {
-            has_moved = true;
+        if action_state.pressed(Action::MoveLeft) {
             transform.translation.x -= SPEED;
-            animator.change_animation(2, true);
+            params.face(-1.);
         }
+    }
 
-        if !has_moved {
-            animator.change_animation(1, false);
+    /// Swings the sword, which strikes on the hit frame of the attack animation.
+    fn attack(
+        mut action_state: ResMut<ActionState>,
+        mut player: Query<(&AnimationGraph, &mut AnimationParams), (With<Player>, Without<Dying>)>,
+    ) {
+        if player.is_empty() {
+            return;
+        }
+
+        let (graph, mut params) = player.single_mut();
+        if graph.state() == AnimationState::Attack || !action_state.consume(Action::Attack) {
+            return;
+        }
+
+        params.trigger(Trigger::Attack);
+    }
+
+    /// Damages everything with `Health` in front of the player as the sword strikes.
+    fn strike(
+        mut animation_events: EventReader<AnimationEvent>,
+        mut damage_events: EventWriter<DamageEvent>,
+        player: Query<(&Player, &Transform, &Animator), Without<Dying>>,
+        targets: Query<(Entity, &Transform), (With<Health>, Without<Player>, Without<Dying>)>,
+    ) {
+        for event in animation_events.iter() {
+            if event.animation != ATTACK_ANIMATION || event.tag != HIT_EVENT {
+                continue;
+            }
+            let (player, player_transform, animator) = match player.get(event.entity) {
+                Ok(player) => player,
+                Err(_) => continue,
+            };
+
+            let facing = if animator.flip_x() { -1. } else { 1. };
+            let position = player_transform.translation.truncate();
+            for (target, transform) in targets.iter() {
+                let offset = transform.translation.truncate() - position;
+                let ahead = offset.x * facing;
+                if (0. ..=player.attack_range).contains(&ahead) && offset.y.abs() <= ATTACK_HEIGHT {
+                    damage_events.send(DamageEvent {
+                        target,
+                        amount: player.attack_damage,
+                    });
+                }
+            }
         }
     }
 
     pub fn system_set() -> SystemSet {
-        SystemSet::on_update(AppState::RunningGame)
-            .with_run_criteria(FixedTimestep::step(1. / 60.))
+        SystemSet::new()
+            .with_run_criteria(AppState::fixed_step(TIME_STEP))
             .with_system(Player::move_player)
+            .with_system(Player::update_armor)
+    }
+
+    /// Reacts to pressed actions and animation events, which only last for the frame they are
+    /// sent on, so would be missed by the fixed steps at high frame rates.
+    pub fn strike_system_set() -> SystemSet {
+        SystemSet::on_update(AppState::RunningGame)
+            .with_system(Player::attack)
+            .with_system(Player::strike)
     }
 }
@@ -0,0 +1,415 @@
+use crate::{
+    dungeon::Dungeon, Action, ActionState, AppState, ArenaDoor, AssetGroups, Boss, BossHealthBar,
+    BossObstacle, Chest, DeathEvent, Item, Monster, Player, Projectile, DEATH_DURATION,
+};
+use bevy::{app::AppExit, ecs::schedule::ShouldRun, prelude::*};
+use bevy_rapier2d::plugin::RapierConfiguration;
+use std::time::Duration;
+
+pub const FONT: &str = "fonts/FiraSans-Bold.ttf";
+
+impl AppState {
+    /// Runs a system set on fixed steps of `step` seconds of `GameTime`, only while the game runs.
+    ///
+    /// A `FixedTimestep` given to a `SystemSet::on_update` replaces its state rather than adding
+    /// to it, which ran fixed step systems while loading, before their resources existed.
+    pub fn fixed_step(step: f32) -> impl System<In = (), Out = ShouldRun> {
+        let mut fixed_step = FixedStep::new(step);
+        (move |time: Res<GameTime>| fixed_step.update(&time)).chain(AppState::in_game)
+    }
+
+    fn in_game(In(should_run): In<ShouldRun>, state: Res<State<AppState>>) -> ShouldRun {
+        if *state.current() == AppState::RunningGame {
+            return should_run;
+        }
+        match should_run {
+            // Keeps the timestep catching up, so that it stays in step with the game's time.
+            ShouldRun::YesAndCheckAgain => ShouldRun::NoAndCheckAgain,
+            _ => ShouldRun::No,
+        }
+    }
+
+    /// Stops the physics outside of the game, so that nothing moves while it is paused.
+    pub(crate) fn freeze_physics(
+        state: Res<State<AppState>>,
+        mut rapier_config: ResMut<RapierConfiguration>,
+    ) {
+        let running = *state.current() == AppState::RunningGame;
+        if rapier_config.physics_pipeline_active != running {
+            rapier_config.physics_pipeline_active = running;
+        }
+    }
+}
+
+/// Time of the game, following Bevy's `Time` unless stepped by hand.
+///
+/// Bevy 0.7 only moves its `Time` along the real clock, so the headless app steps this one
+/// instead, which keeps tests independent of the speed of the machine.
+#[derive(Default)]
+pub struct GameTime {
+    delta: Duration,
+    elapsed: Duration,
+    /// Moved only by `advance`, rather than following Bevy's `Time`.
+    manual: bool,
+}
+impl GameTime {
+    /// Time moved only by `advance`, such as by the headless app.
+    pub fn manual() -> Self {
+        GameTime {
+            manual: true,
+            ..Default::default()
+        }
+    }
+
+    /// Moves the time by `delta` for the next update.
+    pub fn advance(&mut self, delta: Duration) {
+        self.delta = delta;
+        self.elapsed += delta;
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.elapsed.as_secs_f64()
+    }
+
+    pub(crate) fn follow(time: Res<Time>, mut game_time: ResMut<GameTime>) {
+        if !game_time.manual {
+            game_time.advance(time.delta());
+        }
+    }
+}
+
+/// Accumulates the `GameTime` into fixed steps, like a `FixedTimestep` does with Bevy's `Time`.
+struct FixedStep {
+    step: f64,
+    accumulator: f64,
+    /// Whether the steps due this update are being run, so that the time is only added once.
+    looping: bool,
+}
+impl FixedStep {
+    fn new(step: f32) -> Self {
+        FixedStep {
+            step: step as f64,
+            accumulator: 0.,
+            looping: false,
+        }
+    }
+
+    fn update(&mut self, time: &GameTime) -> ShouldRun {
+        if !self.looping {
+            self.accumulator += time.delta().as_secs_f64();
+        }
+        self.looping = self.accumulator >= self.step;
+        if self.looping {
+            self.accumulator -= self.step;
+            ShouldRun::YesAndCheckAgain
+        } else {
+            ShouldRun::No
+        }
+    }
+}
+
+/// Statistics of the current run, displayed once it ends.
+#[derive(Default)]
+pub struct RunSummary {
+    pub seed: u64,
+    pub depth: usize,
+    pub kills: usize,
+    pub time: f32,
+}
+impl RunSummary {
+    pub fn new(seed: u64) -> Self {
+        RunSummary {
+            seed,
+            depth: 1,
+            ..Default::default()
+        }
+    }
+
+    fn tick(time: Res<GameTime>, mut summary: ResMut<RunSummary>) {
+        summary.time += time.delta_seconds();
+    }
+
+    fn count_kills(
+        mut summary: ResMut<RunSummary>,
+        mut death_events: EventReader<DeathEvent>,
+        monsters: Query<(), With<Monster>>,
+    ) {
+        for DeathEvent { entity } in death_events.iter() {
+            if monsters.get(*entity).is_ok() {
+                summary.kills += 1;
+            }
+        }
+    }
+
+    fn end_on_player_death(
+        mut commands: Commands,
+        mut death_events: EventReader<DeathEvent>,
+        run_end: Option<Res<RunEnd>>,
+        players: Query<(), With<Player>>,
+    ) {
+        for DeathEvent { entity } in death_events.iter() {
+            if players.get(*entity).is_ok() && run_end.is_none() {
+                commands.insert_resource(RunEnd::after(AppState::GameOver, DEATH_DURATION));
+            }
+        }
+    }
+
+    fn pause(mut action_state: ResMut<ActionState>, mut state: ResMut<State<AppState>>) {
+        if action_state.consume(Action::Pause) {
+            let _ = state.push(AppState::Paused);
+        }
+    }
+
+    /// Moves the run to the next floor, rebuilt by `setup` when the game resumes.
+    fn descend(mut summary: ResMut<RunSummary>, mut state: ResMut<State<AppState>>) {
+        summary.depth += 1;
+        state.set(AppState::RunningGame).unwrap();
+    }
+
+    fn teardown(
+        mut commands: Commands,
+        query: Query<
+            Entity,
+            Or<(
+                With<Dungeon>,
+                With<Player>,
+                With<Monster>,
+                With<Item>,
+                With<Chest>,
+                With<Boss>,
+                With<BossObstacle>,
+                With<ArenaDoor>,
+                With<BossHealthBar>,
+                With<Projectile>,
+            )>,
+        >,
+    ) {
+        for entity in query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        commands.remove_resource::<RunEnd>();
+    }
+
+    fn format(&self) -> String {
+        let seconds = self.time as u32;
+        format!(
+            "Depth: {}\nKills: {}\nTime: {:02}:{:02}\nSeed: {}",
+            self.depth,
+            self.kills,
+            seconds / 60,
+            seconds % 60,
+            self.seed
+        )
+    }
+
+    pub fn system_set() -> SystemSet {
+        SystemSet::on_update(AppState::RunningGame)
+            .with_system(RunSummary::tick)
+            .with_system(RunSummary::count_kills)
+            .with_system(RunSummary::end_on_player_death)
+            .with_system(RunEnd::tick)
+            .with_system(RunSummary::pause)
+    }
+
+    pub fn teardown_system_set() -> SystemSet {
+        SystemSet::on_exit(AppState::RunningGame).with_system(RunSummary::teardown)
+    }
+
+    pub fn descend_system_set() -> SystemSet {
+        SystemSet::on_enter(AppState::Descending).with_system(RunSummary::descend)
+    }
+}
+
+/// State the run moves to once the timer finishes, such as after the death animation of the player.
+///
+/// Removed by the teardown, so that a pending end doesn't carry over to the next floor or run.
+/// The first end of a run is kept, so the player can't lose once the last boss died.
+pub struct RunEnd {
+    state: AppState,
+    timer: Timer,
+}
+impl RunEnd {
+    pub fn after(state: AppState, seconds: f32) -> Self {
+        RunEnd {
+            state,
+            timer: Timer::from_seconds(seconds, false),
+        }
+    }
+
+    fn tick(
+        mut commands: Commands,
+        time: Res<GameTime>,
+        mut state: ResMut<State<AppState>>,
+        run_end: Option<ResMut<RunEnd>>,
+    ) {
+        if let Some(mut run_end) = run_end {
+            if run_end.timer.tick(time.delta()).finished() {
+                commands.remove_resource::<RunEnd>();
+                // Another transition queued on the same frame, such as a pause, wins.
+                let _ = state.set(run_end.state.clone());
+            }
+        }
+    }
+}
+
+/// Full screen text shown outside of the game, despawned when leaving its state.
+#[derive(Component)]
+pub struct Screen;
+impl Screen {
+    pub fn spawn(commands: &mut Commands, asset_server: &AssetServer, title: &str, body: String) {
+        let font = asset_server.load(FONT);
+
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                    flex_direction: FlexDirection::ColumnReverse,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                color: Color::rgba(0., 0., 0., 0.7).into(),
+                ..Default::default()
+            })
+            .insert(Screen)
+            .with_children(|screen| {
+                screen.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        title,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 60.,
+                            color: Color::WHITE,
+                        },
+                        Default::default(),
+                    ),
+                    ..Default::default()
+                });
+                screen.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        body,
+                        TextStyle {
+                            font,
+                            font_size: 24.,
+                            color: Color::WHITE,
+                        },
+                        TextAlignment {
+                            horizontal: HorizontalAlign::Center,
+                            ..Default::default()
+                        },
+                    ),
+                    ..Default::default()
+                });
+            });
+    }
+
+    pub fn despawn(mut commands: Commands, query: Query<Entity, With<Screen>>) {
+        for entity in query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    pub fn main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+        Screen::spawn(
+            &mut commands,
+            &asset_server,
+            "Rogue Like",
+            "Enter: start\nEscape: quit".to_string(),
+        );
+    }
+
+    pub fn paused(mut commands: Commands, asset_server: Res<AssetServer>) {
+        Screen::spawn(
+            &mut commands,
+            &asset_server,
+            "Paused",
+            "Pause: resume\nC: controls\nM: main menu".to_string(),
+        );
+    }
+
+    pub fn game_over(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        summary: Res<RunSummary>,
+    ) {
+        Screen::spawn(
+            &mut commands,
+            &asset_server,
+            "Game Over",
+            format!("{}\n\nEnter: restart\nM: main menu", summary.format()),
+        );
+    }
+
+    pub fn victory(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        summary: Res<RunSummary>,
+    ) {
+        Screen::spawn(
+            &mut commands,
+            &asset_server,
+            "Victory",
+            format!("{}\n\nEnter: new run\nM: main menu", summary.format()),
+        );
+    }
+
+    /// Lists the assets which failed to load.
+    pub fn loading_failed(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        groups: Res<AssetGroups>,
+    ) {
+        for error in groups.errors() {
+            error!("Failed to load {}", error);
+        }
+        Screen::spawn(
+            &mut commands,
+            &asset_server,
+            "Loading Failed",
+            format!("{}\n\nEscape: quit", groups.errors().join("\n")),
+        );
+    }
+
+    pub fn start_run(
+        mut commands: Commands,
+        mut keyboard_input: ResMut<Input<KeyCode>>,
+        mut state: ResMut<State<AppState>>,
+    ) {
+        if keyboard_input.just_pressed(KeyCode::Return) {
+            keyboard_input.clear();
+            commands.insert_resource(RunSummary::new(rand::random()));
+            // Pressing several keys on the same frame keeps the first transition.
+            let _ = state.set(AppState::RunningGame);
+        }
+    }
+
+    pub fn resume(mut action_state: ResMut<ActionState>, mut state: ResMut<State<AppState>>) {
+        if action_state.consume(Action::Pause) {
+            let _ = state.pop();
+        }
+    }
+
+    pub fn quit(keyboard_input: Res<Input<KeyCode>>, mut app_exit_events: EventWriter<AppExit>) {
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            app_exit_events.send(AppExit);
+        }
+    }
+
+    pub fn back_to_main_menu(
+        mut keyboard_input: ResMut<Input<KeyCode>>,
+        mut state: ResMut<State<AppState>>,
+    ) {
+        if keyboard_input.just_pressed(KeyCode::M) {
+            keyboard_input.clear();
+            let _ = state.replace(AppState::MainMenu);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use bevy::prelude::{KeyCode, Transform, Vec2, With};
+use rogue_like::{dungeon::Dungeon, *};
+
+const SEED: u64 = 42;
+
+fn running_game() -> HeadlessApp {
+    let mut game = HeadlessApp::new();
+    game.start_run(SEED);
+    game
+}
+
+#[test]
+fn loads_assets_into_main_menu() {
+    let game = HeadlessApp::new();
+    assert_eq!(game.state(), AppState::MainMenu);
+}
+
+#[test]
+fn generates_dungeon_with_boss_arena() {
+    let mut game = running_game();
+    assert_eq!(game.state(), AppState::RunningGame);
+
+    let dungeon = game.dungeon().expect("no dungeon");
+    let (min, _) = Dungeon::arena();
+    let (x, y) = dungeon.cell(min).expect("arena outside of the dungeon");
+    assert!(dungeon.is_terrain(x, y - 1), "arena without floor");
+    assert!(!dungeon.is_terrain(x, y), "arena filled with terrain");
+
+    assert!(game.player().is_some());
+    assert!(!game.monsters().is_empty());
+}
+
+#[test]
+fn spawns_flying_monsters_in_open_air() {
+    let mut game = running_game();
+    let dungeon = game.dungeon().expect("no dungeon");
+    let spawns = dungeon.flying_monster_spawns();
+    assert!(!spawns.is_empty(), "no flying monster cells");
+    for &spawn in &spawns {
+        let (x, y) = dungeon.cell(spawn).unwrap();
+        assert!(!dungeon.is_terrain(x, y), "flying monster inside terrain");
+    }
+
+    let birds: Vec<Vec2> = game
+        .monsters()
+        .into_iter()
+        .filter(|&monster| game.get::<Monster>(monster).unwrap().species() == Species::Bird)
+        .map(|monster| game.position(monster).unwrap())
+        .collect();
+    assert_eq!(birds.len(), spawns.len());
+    assert!(spawns.iter().all(|spawn| birds.contains(spawn)));
+}
+
+#[test]
+fn opening_chests_drops_loot() {
+    let mut game = running_game();
+    let player = game.player().unwrap();
+    let chests = game.dungeon().unwrap().chest_spawns();
+    assert!(!chests.is_empty(), "no chest cells");
+
+    let chest = game
+        .world()
+        .query_filtered::<&Transform, With<Chest>>()
+        .iter(game.world())
+        .next()
+        .map(|transform| transform.translation)
+        .expect("no chest");
+    game.world()
+        .get_mut::<Transform>(player)
+        .unwrap()
+        .translation = chest;
+    game.press(Action::Interact);
+
+    let dropped = |game: &mut HeadlessApp| {
+        let mut items = game.world().query_filtered::<(), With<Item>>();
+        items.iter(game.world()).next().is_some()
+    };
+    assert!(game.run_until(10, dropped), "chest dropped nothing");
+}
+
+#[test]
+fn player_falls_onto_terrain() {
+    let mut game = running_game();
+    let player = game.player().unwrap();
+    let start = game.position(player).unwrap();
+
+    game.run(180);
+    let landed = game.position(player).unwrap();
+    assert!(landed.y < start.y, "player did not fall");
+
+    game.run(30);
+    let rested = game.position(player).unwrap();
+    assert!(
+        (rested.y - landed.y).abs() < 1.,
+        "player fell through the floor"
+    );
+}
+
+#[test]
+fn player_moves_with_input() {
+    let mut game = running_game();
+    let player = game.player().unwrap();
+    game.run(60);
+
+    let start = game.position(player).unwrap();
+    game.press(Action::MoveRight);
+    game.run(10);
+    game.release(Action::MoveRight);
+    let moved = game.position(player).unwrap();
+    assert!(moved.x > start.x, "player did not move right");
+
+    game.run(10);
+    let stopped = game.position(player).unwrap();
+    assert!(
+        (stopped.x - moved.x).abs() < 1.,
+        "player kept moving after release"
+    );
+
+    game.press(Action::MoveLeft);
+    game.run(10);
+    game.release(Action::MoveLeft);
+    assert!(
+        game.position(player).unwrap().x < stopped.x,
+        "player did not move left"
+    );
+}
+
+#[test]
+fn pausing_freezes_the_game() {
+    let mut game = running_game();
+    game.run(60);
+
+    game.press(Action::Pause);
+    assert!(game.run_until(10, |game| game.state() == AppState::Paused));
+    game.release(Action::Pause);
+    // Physics stop on the update after pausing.
+    game.tick();
+
+    let positions = |game: &mut HeadlessApp| -> Vec<Vec2> {
+        let mut entities = game.monsters();
+        entities.extend(game.player());
+        entities
+            .into_iter()
+            .map(|entity| {
+                game.get::<Transform>(entity)
+                    .unwrap()
+                    .translation
+                    .truncate()
+            })
+            .collect()
+    };
+    let before = positions(&mut game);
+    game.run(30);
+    assert_eq!(positions(&mut game), before);
+
+    game.press(Action::Pause);
+    assert!(game.run_until(10, |game| game.state() == AppState::RunningGame));
+}
+
+#[test]
+fn monsters_die_from_damage() {
+    let mut game = running_game();
+    let monsters = game.monsters();
+    let monster = monsters[0];
+    assert_eq!(game.get::<Health>(monster).unwrap().fraction(), 1.);
+
+    game.send(DamageEvent {
+        target: monster,
+        amount: 1.,
+    });
+    game.run(1);
+    let fraction = game.get::<Health>(monster).unwrap().fraction();
+    assert!(fraction < 1., "monster took no damage");
+
+    assert!(game.run_until(120, |game| game.get::<Invulnerable>(monster).is_none()));
+    game.send(DamageEvent {
+        target: monster,
+        amount: 1000.,
+    });
+    assert!(game.run_until(120, |game| game.get::<Health>(monster).is_none()));
+    assert_eq!(game.monsters().len(), monsters.len() - 1);
+    assert_eq!(game.world().resource::<RunSummary>().kills, 1);
+}
+
+#[test]
+fn only_the_first_hit_of_an_update_lands() {
+    let mut game = running_game();
+    let monster = game.monsters()[0];
+
+    game.send(DamageEvent {
+        target: monster,
+        amount: 1.,
+    });
+    game.send(DamageEvent {
+        target: monster,
+        amount: 1000.,
+    });
+    game.run(1);
+    let health = game.get::<Health>(monster).expect("monster died");
+    assert!(!health.is_dead(), "second hit landed");
+    assert!(health.fraction() < 1., "first hit missed");
+}
+
+#[test]
+fn player_death_ends_the_run() {
+    let mut game = running_game();
+    let player = game.player().unwrap();
+
+    game.send(DamageEvent {
+        target: player,
+        amount: 1000.,
+    });
+    assert!(game.run_until(120, |game| game.state() == AppState::GameOver));
+    assert!(game.player().is_none());
+    assert!(game.dungeon().is_none());
+}
+
+#[test]
+fn screen_keys_pressed_together_pick_one_transition() {
+    let mut game = running_game();
+    let player = game.player().unwrap();
+    game.send(DamageEvent {
+        target: player,
+        amount: 1000.,
+    });
+    assert!(game.run_until(120, |game| game.state() == AppState::GameOver));
+
+    game.press_key(KeyCode::Return);
+    game.press_key(KeyCode::M);
+    assert!(game.run_until(10, |game| game.state() != AppState::GameOver));
+}
+
+#[test]
+fn leaving_a_run_cancels_its_pending_end() {
+    let mut game = running_game();
+    game.world()
+        .insert_resource(RunEnd::after(AppState::Victory, 1.));
+
+    game.press(Action::Pause);
+    assert!(game.run_until(10, |game| game.state() == AppState::Paused));
+    game.press_key(KeyCode::M);
+    assert!(game.run_until(10, |game| game.state() == AppState::MainMenu));
+    assert!(game.world().get_resource::<RunEnd>().is_none());
+}